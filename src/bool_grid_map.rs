@@ -0,0 +1,130 @@
+use crate::{GridPoint, RangeMetric, VisibilityMap, VisibilityMapUtility};
+
+/// A simple map of opaque and visible tiles stored in two flat `Vec<bool>`s.
+///
+/// Tiles are indexed as `y * width + x`, with `[0,0]` being the first tile.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoolGridMap {
+    width: usize,
+    height: usize,
+    opaque: Vec<bool>,
+    visible: Vec<bool>,
+}
+
+impl BoolGridMap {
+    /// Create a map of the given size with no opaque or visible tiles.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            opaque: vec![false; width * height],
+            visible: vec![false; width * height],
+        }
+    }
+
+    /// Create a map from a list of opaque tiles, in `y * width + x` order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `opaque` is not `width * height` long.
+    pub fn from_opaque(width: usize, height: usize, opaque: Vec<bool>) -> Self {
+        assert_eq!(width * height, opaque.len());
+        Self {
+            width,
+            height,
+            opaque,
+            visible: vec![false; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Set whether the tile at `p` is opaque. Out of bounds tiles are ignored.
+    pub fn set_opaque(&mut self, p: impl GridPoint, opaque: bool) {
+        if let Some(i) = self.index(p) {
+            self.opaque[i] = opaque;
+        }
+    }
+
+    /// Whether the tile at `p` is visible. Out of bounds tiles are never visible.
+    pub fn is_visible(&self, p: impl GridPoint) -> bool {
+        match self.index(p) {
+            Some(i) => self.visible[i],
+            None => false,
+        }
+    }
+
+    /// The visible tiles, in `y * width + x` order.
+    pub fn visible(&self) -> &[bool] {
+        &self.visible
+    }
+
+    /// The opaque tiles, in `y * width + x` order.
+    pub fn opaque(&self) -> &[bool] {
+        &self.opaque
+    }
+
+    fn index(&self, p: impl GridPoint) -> Option<usize> {
+        self.is_in_bounds(p)
+            .then(|| p.y() as usize * self.width + p.x() as usize)
+    }
+}
+
+impl VisibilityMap for BoolGridMap {
+    fn is_opaque(&self, p: impl GridPoint) -> bool {
+        match self.index(p) {
+            Some(i) => self.opaque[i],
+            None => true,
+        }
+    }
+
+    fn is_in_bounds(&self, p: impl GridPoint) -> bool {
+        let p = p.as_ivec2();
+        p.x >= 0 && p.y >= 0 && (p.x as usize) < self.width && (p.y as usize) < self.height
+    }
+
+    fn set_visible(&mut self, p: impl GridPoint) {
+        if let Some(i) = self.index(p) {
+            self.visible[i] = true;
+        }
+    }
+
+    fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+        RangeMetric::Euclidean.dist(a, b)
+    }
+
+    fn in_range(&self, a: impl GridPoint, b: impl GridPoint, range: i32) -> bool {
+        RangeMetric::Euclidean.in_range(a, b, range)
+    }
+}
+
+impl VisibilityMapUtility for BoolGridMap {
+    fn toggle_opaque(&mut self, p: impl GridPoint) {
+        if let Some(i) = self.index(p) {
+            self.opaque[i] = !self.opaque[i];
+        }
+    }
+
+    fn toggle_visible(&mut self, p: impl GridPoint) {
+        if let Some(i) = self.index(p) {
+            self.visible[i] = !self.visible[i];
+        }
+    }
+
+    /// Clear all opaque tiles from the map
+    fn clear_opaque(&mut self) {
+        self.opaque.iter_mut().for_each(|v| *v = false);
+    }
+
+    /// Clear all visible tiles from the map
+    fn clear_visible(&mut self) {
+        self.visible.iter_mut().for_each(|v| *v = false);
+    }
+}
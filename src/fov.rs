@@ -0,0 +1,3058 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::ControlFlow,
+};
+
+use glam::{IVec2, Vec2};
+use sark_grids::Grid;
+
+use crate::{
+    internal::Slope, Blend, DiagonalRule, Falloff, GridPoint, OpaqueOrigin, OutOfBounds,
+    RangeBound, RangeMetric, Symmetry, VisibilityMap,
+};
+
+/// Compute the fov in a map from the given position.
+///
+/// The origin may lie outside the map's bounds, in which case only the in-bounds
+/// tiles visible from it are marked. A range of 0 or less marks only the origin.
+///
+/// Passing a very large range, such as `i32::MAX`, is an accepted way to see the
+/// whole map. Opaque and out of bounds tiles end the search, so the work done is
+/// limited by the size of the map rather than the range.
+///
+/// An opaque origin, such as a creature standing inside a wall, only sees
+/// itself. Use [FovConfig::opaque_origin] to let it see the tiles around it.
+///
+/// Internal slope math is done relative to the origin in 64 bit integers, so
+/// large coordinates are fine as long as every position within `range` of the
+/// origin fits in an `i32`.
+pub fn compute<T: VisibilityMap>(origin: impl GridPoint, range: i32, map: &mut T) {
+    compute_range(Params::new(origin, Range::Radius(range)), map);
+}
+
+/// Compute the fov in a map indexed by local coordinates from a position given
+/// in world coordinates.
+///
+/// `offset` is the world position of the map's `[0,0]` tile. It's subtracted
+/// from `origin`, so the map is only ever called with local positions.
+pub fn compute_local<T: VisibilityMap>(
+    origin: impl GridPoint,
+    offset: impl GridPoint,
+    range: i32,
+    map: &mut T,
+) {
+    compute(origin.as_ivec2() - offset.as_ivec2(), range, map);
+}
+
+/// Compute the fov in a `width` by `height` map starting at `[0,0]`, reading
+/// opaque tiles from `is_opaque` and passing each visible tile to
+/// `mark_visible`.
+///
+/// This doesn't require implementing [VisibilityMap] or using any types from
+/// `sark_grids`. Tiles outside the map block vision and are never passed to
+/// either closure. The range is capped to the furthest tile in the map.
+pub fn compute_wh(
+    origin: IVec2,
+    range: i32,
+    width: i32,
+    height: i32,
+    is_opaque: impl Fn(IVec2) -> bool,
+    mut mark_visible: impl FnMut(IVec2),
+) {
+    let size = IVec2::new(width, height);
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(clamp_range(origin, range, size))),
+        &mut SizedMap { size, is_opaque },
+        &mut |_, tile| {
+            mark_visible(tile.pos);
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Like [compute_wh], but returns an error for inputs that can't produce a
+/// meaningful fov instead of computing it anyway.
+///
+/// Unlike [compute_wh], the origin must be inside the map.
+pub fn try_compute_wh(
+    origin: IVec2,
+    range: i32,
+    width: i32,
+    height: i32,
+    is_opaque: impl Fn(IVec2) -> bool,
+    mark_visible: impl FnMut(IVec2),
+) -> Result<(), FovError> {
+    if width <= 0 || height <= 0 {
+        return Err(FovError::EmptyGrid);
+    }
+    if origin.x < 0 || origin.y < 0 || origin.x >= width || origin.y >= height {
+        return Err(FovError::OriginOutOfBounds(origin));
+    }
+    compute_wh(origin, range, width, height, is_opaque, mark_visible);
+    Ok(())
+}
+
+/// The reasons [try_compute_wh] can fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FovError {
+    /// The map has no tiles.
+    EmptyGrid,
+    /// The origin is outside of the map.
+    OriginOutOfBounds(IVec2),
+}
+
+impl std::fmt::Display for FovError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FovError::EmptyGrid => write!(f, "the map has no tiles"),
+            FovError::OriginOutOfBounds(p) => write!(f, "origin {:?} is outside of the map", p),
+        }
+    }
+}
+
+impl std::error::Error for FovError {}
+
+/// Render the fov in a `width` by `height` map as a string, for debugging.
+///
+/// Each row of the map is a line of text, with the top row (the highest `y`)
+/// first. The origin is drawn as `@`, visible tiles as `.`, visible opaque
+/// tiles as `#` and hidden tiles as a space.
+#[cfg(feature = "debug")]
+pub fn to_string(
+    origin: IVec2,
+    range: i32,
+    width: i32,
+    height: i32,
+    is_opaque: impl Fn(IVec2) -> bool,
+) -> String {
+    let (w, h) = (width.max(0) as usize, height.max(0) as usize);
+    let mut glyphs = vec![' '; w * h];
+    compute_wh(origin, range, width, height, &is_opaque, |p| {
+        let i = p.y as usize * w + p.x as usize;
+        glyphs[i] = match p == origin {
+            true => '@',
+            false if is_opaque(p) => '#',
+            false => '.',
+        };
+    });
+    let mut string = String::with_capacity((w + 1) * h);
+    for row in glyphs.chunks(w.max(1)).rev() {
+        string.extend(row);
+        string.push('\n');
+    }
+    string
+}
+
+/// Mark every tile within range of the given position as visible, ignoring
+/// opaque tiles.
+///
+/// This skips the shadowcasting entirely, so it's a cheap way to light open
+/// areas with nothing to block vision. On a map without opaque tiles it marks
+/// the same tiles as [compute].
+///
+/// Every tile in the square of `range` around the origin is checked, including
+/// those outside the map, so this takes O(range²) and is only meant for small
+/// ranges. Unlike [compute], a range like `i32::MAX` won't finish. The square
+/// must also fit inside the range of an `i32`.
+pub fn compute_disc<T: VisibilityMap>(origin: impl GridPoint, range: i32, map: &mut T) {
+    let origin = origin.as_ivec2();
+    for y in -range..=range {
+        for x in -range..=range {
+            let p = origin + IVec2::new(x, y);
+            if map.is_in_bounds(p) && map.in_range(origin, p, range) {
+                map.set_visible(p);
+            }
+        }
+    }
+    if range < 0 && map.is_in_bounds(origin) {
+        map.set_visible(origin);
+    }
+}
+
+/// Compute the fov in a map from the given position with a fractional range.
+///
+/// This allows the fov to grow and shrink smoothly, for example with a torch
+/// that slowly dims, rather than snapping between whole tile ranges.
+pub fn compute_f32<T: VisibilityMap>(origin: impl GridPoint, range: f32, map: &mut T) {
+    compute_range(Params::new(origin, Range::Fractional(range)), map);
+}
+
+/// Compute the fov in a map from the given position, clipping the fov to a
+/// rectangle extending the given number of tiles in each direction.
+///
+/// `up` extends along +y and `right` along +x. Walls inside the rectangle
+/// still block vision as usual.
+pub fn compute_box<T: VisibilityMap>(
+    origin: impl GridPoint,
+    up: i32,
+    down: i32,
+    left: i32,
+    right: i32,
+    map: &mut T,
+) {
+    compute_range(
+        Params::new(
+            origin,
+            Range::Box(IVec2::new(-left, -down), IVec2::new(right, up)),
+        ),
+        map,
+    );
+}
+
+/// Compute the fov in a map from the given position, clipping the fov to an
+/// ellipse with independent radii along the x and y axes.
+pub fn compute_ellipse<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range_x: i32,
+    range_y: i32,
+    map: &mut T,
+) {
+    compute_range(
+        Params::new(origin, Range::Ellipse(IVec2::new(range_x, range_y))),
+        map,
+    );
+}
+
+/// Compute the fov in a map from the given position and return the visible
+/// tiles.
+///
+/// The map itself is not modified. Each visible tile is returned once, ordered
+/// by row then column.
+pub fn compute_tiles<T: VisibilityMap>(origin: impl GridPoint, range: i32, map: &T) -> Vec<IVec2> {
+    let mut tiles = Vec::new();
+    compute_into_vec(origin, range, map, &mut tiles);
+    tiles
+}
+
+/// Compute the fov in a map from the given position and write the visible
+/// tiles into `tiles`, reusing its allocation.
+///
+/// `tiles` is cleared first. Each visible tile is written once, ordered by row
+/// then column. The map itself is not modified.
+pub fn compute_into_vec<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+    tiles: &mut Vec<IVec2>,
+) {
+    let mut collector = TileCollector {
+        map,
+        tiles: std::mem::take(tiles),
+    };
+    collector.tiles.clear();
+    compute(origin, range, &mut collector);
+
+    *tiles = collector.tiles;
+    tiles.sort_unstable_by_key(|p| (p.y, p.x));
+    tiles.dedup();
+}
+
+/// Compute the fov in a map from the given position and return the visible
+/// tiles on its outline - those with at least one non-visible cardinal
+/// neighbour.
+///
+/// This includes both the edge of the range and the edges of any shadows. Tiles
+/// are sorted by their y, then x coordinate. The map itself is not modified.
+pub fn compute_frontier<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+) -> Vec<IVec2> {
+    let tiles = compute_tiles(origin, range, map);
+    let visible: HashSet<_> = tiles.iter().copied().collect();
+    tiles
+        .into_iter()
+        .filter(|p| {
+            [IVec2::X, IVec2::Y, -IVec2::X, -IVec2::Y]
+                .iter()
+                .any(|d| !visible.contains(&(*p + *d)))
+        })
+        .collect()
+}
+
+/// Compute the fov in a map from the given position and return the visible
+/// tiles on the edge of its range - those whose distance from the origin
+/// rounds to `range`.
+///
+/// Unlike [compute_frontier] this doesn't include the edges of shadows. Tiles
+/// are sorted by their y, then x coordinate. The map itself is not modified.
+pub fn compute_perimeter<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+) -> Vec<IVec2> {
+    let mut tiles = Vec::new();
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut ReadOnly(map),
+        &mut |_, tile| {
+            if tile.dist.round() as i32 == range {
+                tiles.push(tile.pos);
+            }
+            ControlFlow::Continue(())
+        },
+    );
+    tiles.sort_unstable_by_key(|p| (p.y, p.x));
+    tiles.dedup();
+    tiles
+}
+
+/// Compute the fov in a map from the given position and return the visible
+/// tiles, nearest to the origin first.
+///
+/// Tiles are in the same order as [compute_radial], sorted by the map's
+/// [VisibilityMap::dist] from the origin with ties sorted by their y, then x
+/// coordinate. The map itself is not modified.
+pub fn compute_sorted<T: VisibilityMap>(origin: impl GridPoint, range: i32, map: &T) -> Vec<IVec2> {
+    let mut tiles = Vec::new();
+    compute_radial(origin, range, map, |p, _| tiles.push(p));
+    tiles
+}
+
+/// Compute the fov in a map from the given position, passing each visible tile
+/// and its distance from the origin to `mark_visible` exactly once, nearest
+/// to the origin first.
+///
+/// Tiles are ordered by the same distance used to clip the fov to its range,
+/// with ties ordered by their y, then x coordinate. Unlike the other compute
+/// functions the tiles are collected and sorted before any are passed on, which
+/// costs an allocation and a sort. Use [compute_sorted] to get the tiles as a
+/// `Vec` instead. The map itself is not modified.
+pub fn compute_radial<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+    mut mark_visible: impl FnMut(IVec2, f32),
+) {
+    let mut tiles = Vec::new();
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut ReadOnly(map),
+        &mut |_, tile| {
+            tiles.push((tile.pos, tile.dist));
+            ControlFlow::Continue(())
+        },
+    );
+    tiles.sort_unstable_by(|(a, da), (b, db)| da.total_cmp(db).then((a.y, a.x).cmp(&(b.y, b.x))));
+    tiles.dedup_by_key(|(p, _)| *p);
+    for (p, dist) in tiles {
+        mark_visible(p, dist);
+    }
+}
+
+/// Compute the change in visible tiles when the origin moves from `from` to `to`.
+///
+/// Both fovs are computed in full. The map itself is not modified.
+#[doc(alias = "fov_moved")]
+pub fn compute_delta<T: VisibilityMap>(
+    from: impl GridPoint,
+    to: impl GridPoint,
+    range: i32,
+    map: &T,
+) -> FovDelta {
+    let before = compute_tiles(from, range, map);
+    let after = compute_tiles(to, range, map);
+    let was_visible: HashSet<_> = before.iter().copied().collect();
+    let is_visible: HashSet<_> = after.iter().copied().collect();
+    let added = after
+        .into_iter()
+        .filter(|p| !was_visible.contains(p))
+        .collect();
+    let removed = before
+        .into_iter()
+        .filter(|p| !is_visible.contains(p))
+        .collect();
+    FovDelta { added, removed }
+}
+
+/// The tiles that became visible or hidden between two fov computations.
+///
+/// Tiles are sorted by their y, then x coordinate.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FovDelta {
+    /// Tiles that are now visible but weren't before.
+    pub added: Vec<IVec2>,
+    /// Tiles that were visible before but aren't now.
+    pub removed: Vec<IVec2>,
+}
+
+impl FovDelta {
+    /// Whether the visible tiles are unchanged.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Compute the fov in a map from the given position and write it into a
+/// separate grid of visible tiles.
+///
+/// `visible` is cleared first. Tiles outside of its bounds are ignored.
+pub fn compute_into_grid<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+    visible: &mut Grid<bool>,
+) {
+    visible.iter_mut().for_each(|v| *v = false);
+    compute(origin, range, &mut GridWriter { map, visible });
+}
+
+/// Compute the fov in a map from the given position and add the visible tiles
+/// to a grid of explored tiles, passing each tile that wasn't already explored
+/// to `newly_revealed`.
+///
+/// Tiles are never removed from `explored`, so it can be kept between calls to
+/// uncover the map as it's explored. Tiles outside of its bounds are ignored.
+/// The map itself is not modified.
+pub fn compute_reveal<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+    explored: &mut Grid<bool>,
+    mut newly_revealed: impl FnMut(IVec2),
+) {
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut ReadOnly(map),
+        &mut |_, tile| {
+            if explored.in_bounds(tile.pos) && !explored[tile.pos] {
+                explored[tile.pos] = true;
+                newly_revealed(tile.pos);
+            }
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Compute the fov in a map from the given position and write it into a flat
+/// slice of visible tiles, indexed as `y * width + x`.
+///
+/// The whole of `visible` is cleared first. Tiles outside of `width` and
+/// `height` are ignored.
+///
+/// # Panics
+///
+/// Panics if `visible` is not `width * height` long.
+pub fn compute_into_slice<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    width: usize,
+    height: usize,
+    map: &T,
+    visible: &mut [bool],
+) {
+    assert_eq!(width * height, visible.len());
+    visible.iter_mut().for_each(|v| *v = false);
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut ReadOnly(map),
+        &mut |_, tile| {
+            let p = tile.pos;
+            if p.x >= 0 && p.y >= 0 && (p.x as usize) < width && (p.y as usize) < height {
+                visible[p.y as usize * width + p.x as usize] = true;
+            }
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Compute the fov in a map from the given position, passing each visible tile
+/// to `mark_visible` exactly once.
+///
+/// Unlike [compute], which may mark the same tile more than once, already
+/// visited tiles are tracked for the duration of the call. The map itself is not
+/// modified.
+pub fn compute_unique<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+    mark_visible: impl FnMut(IVec2),
+) {
+    Fov::new().compute(origin, range, map, mark_visible);
+}
+
+/// Compute the fov in a map from the given position, passing each visible tile
+/// to `mark_visible` exactly once as the point type `P`.
+///
+/// Positions are converted from [IVec2] right before being passed on, so any
+/// type with a `From<IVec2>` conversion, like `(i32, i32)` or `[i32; 2]`, can be
+/// used. The map itself is not modified.
+pub fn compute_as<P: From<IVec2>, T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+    mut mark_visible: impl FnMut(P),
+) {
+    compute_unique(origin, range, map, |p| mark_visible(P::from(p)));
+}
+
+/// Count the number of unique tiles visible from the given position.
+///
+/// The map itself is not modified.
+pub fn visible_tile_count<T: VisibilityMap>(origin: impl GridPoint, range: i32, map: &T) -> usize {
+    let mut count = 0;
+    compute_unique(origin, range, map, |_| count += 1);
+    count
+}
+
+/// Compute the fov in a map from the given position, passing each visible tile
+/// to `mark_visible` until it returns [ControlFlow::Break].
+///
+/// Returns [ControlFlow::Break] if the computation was stopped early. The map
+/// itself is not modified.
+pub fn compute_until<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+    mut mark_visible: impl FnMut(IVec2) -> ControlFlow<()>,
+) -> ControlFlow<()> {
+    compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut ReadOnly(map),
+        &mut |_, tile| mark_visible(tile.pos),
+    )
+}
+
+/// Compute the fov in a map from the given position, stopping early if
+/// `should_cancel` returns true.
+///
+/// `should_cancel` is polled before each visible tile is marked, so it should be
+/// cheap - for example checking an [AtomicBool](std::sync::atomic::AtomicBool)
+/// or a deadline. Tiles marked before cancelling are left visible.
+///
+/// Returns [ControlFlow::Break] if the computation was cancelled.
+pub fn compute_cancellable<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &mut T,
+    mut should_cancel: impl FnMut() -> bool,
+) -> ControlFlow<()> {
+    compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        map,
+        &mut |map, tile| {
+            if should_cancel() {
+                return ControlFlow::Break(());
+            }
+            map.set_visible(tile.pos);
+            ControlFlow::Continue(())
+        },
+    )
+}
+
+/// Compute the fov in a map from the given position, passing each visible tile
+/// to `mark_visible` along with whether or not it's a wall (opaque).
+///
+/// Walls facing the origin are visible even though the tiles behind them are
+/// not, so this can be used to draw lit wall faces differently from lit floors.
+/// The map itself is not modified.
+pub fn compute_with_walls<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+    mut mark_visible: impl FnMut(IVec2, bool),
+) {
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut ReadOnly(map),
+        &mut |_, tile| {
+            mark_visible(tile.pos, tile.opaque);
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Compute the fov in a map from the given position, where tiles can partially
+/// obscure vision, passing each visible tile to `mark_visible`.
+///
+/// `vision_cost` is the cost of seeing through a non-opaque tile and should be
+/// `1.0` for clear tiles. A tile is visible if the total cost of the tiles
+/// between it and the origin, plus one for the tile itself, is within `range`.
+/// Tiles with an infinite cost block vision like opaque tiles. The map itself is
+/// not modified.
+///
+/// The cost is added up along a Bresenham line from the origin to each visible
+/// tile, which only approximates the sightlines the shadowcasting uses. Walking
+/// a line per tile makes this O(tiles × range). Opaque or out of bounds tiles on
+/// the line are left to the shadowcasting.
+pub fn compute_with_cost<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+    vision_cost: impl Fn(IVec2) -> f32,
+    mut mark_visible: impl FnMut(IVec2),
+) {
+    let origin = origin.as_ivec2();
+    let mut map = OpaqueIf {
+        map,
+        opaque: |p| vision_cost(p).is_infinite(),
+    };
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut map,
+        &mut |map, tile| {
+            let cost: f32 = Line::new(origin, tile.pos)
+                .take_while(|p| *p != tile.pos)
+                .map(|p| match !map.is_in_bounds(p) || map.is_opaque(p) {
+                    // Already handled by the shadowcasting
+                    true => 1.0,
+                    false => vision_cost(p),
+                })
+                .sum();
+            if cost + 1.0 <= range as f32 {
+                mark_visible(tile.pos);
+            }
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Compute the fov in a map from the given position, where tiles can block
+/// vision from some directions but not others, passing each visible tile to
+/// `mark_visible`.
+///
+/// `blocks(from, to)` is passed the origin and the tile being checked, and
+/// should return true if the tile blocks vision coming from that direction.
+/// The map's opaque tiles always block vision. The map itself is not modified.
+pub fn compute_directional<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+    blocks: impl Fn(IVec2, IVec2) -> bool,
+    mut mark_visible: impl FnMut(IVec2),
+) {
+    let origin = origin.as_ivec2();
+    let mut map = OpaqueIf {
+        map,
+        opaque: |p| blocks(origin, p),
+    };
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut map,
+        &mut |_, tile| {
+            mark_visible(tile.pos);
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Compute the fov in a map from the given position, where vision can pass
+/// through up to `wall_penetration` opaque tiles, passing each visible tile to
+/// `mark_visible` exactly once.
+///
+/// Tiles visible as normal are always visible. Any other in range tile is
+/// visible if a straight line to it crosses no more than `wall_penetration`
+/// opaque tiles, so a `wall_penetration` of 0 is the same as [compute_unique].
+/// The map itself is not modified.
+///
+/// The line is a Bresenham line, which only approximates the sightlines of the
+/// shadowcasting, and tiles on it that are out of bounds aren't counted. The
+/// tiles to check are found with an fov that ignores opaque tiles, so like
+/// [compute] the work is limited by the size of the map rather than the range,
+/// but walking a line to each of them makes this O(tiles × range).
+pub fn compute_with_penetration<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    wall_penetration: u32,
+    map: &T,
+    mut mark_visible: impl FnMut(IVec2),
+) {
+    let origin = origin.as_ivec2();
+    let mut seen = HashSet::new();
+    compute_unique(origin, range, map, |p| {
+        seen.insert(p);
+        mark_visible(p);
+    });
+    if wall_penetration == 0 {
+        return;
+    }
+
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut SeeThrough(map),
+        &mut |_, tile| {
+            let p = tile.pos;
+            if !seen.insert(p) {
+                return ControlFlow::Continue(());
+            }
+            let walls = Line::new(origin, p)
+                .take_while(|l| *l != p)
+                .filter(|l| map.is_in_bounds(*l) && map.is_opaque(*l))
+                .count();
+            if walls <= wall_penetration as usize {
+                mark_visible(p);
+            }
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Compute the fov in a map from the given position, where tiles have a height
+/// that can be seen over, passing each visible tile to `mark_visible`.
+///
+/// The sightline to a tile runs from `eye_height` above the origin down (or up)
+/// to the top of the tile. A tile is hidden if anything along the way is taller
+/// than the sightline where it crosses it. Tiles taller than `eye_height`, along
+/// with the map's opaque tiles, always block vision. The map itself is not
+/// modified.
+///
+/// Heights are sampled along a Bresenham line to each visible tile rather than
+/// the exact sightline, so a tile just off the line can be missed. Each tile
+/// walks its own line, for O(tiles × range) in total.
+pub fn compute_with_height<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    eye_height: u8,
+    map: &T,
+    height: impl Fn(IVec2) -> u8,
+    mut mark_visible: impl FnMut(IVec2),
+) {
+    let origin = origin.as_ivec2();
+    let mut map = OpaqueIf {
+        map,
+        opaque: |p| height(p) > eye_height,
+    };
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut map,
+        &mut |map, tile| {
+            let eye = eye_height as f32;
+            let target = height(tile.pos) as f32;
+            let steps = (tile.pos - origin).abs().max_element() as f32;
+            let hidden = Line::new(origin, tile.pos)
+                .take_while(|p| *p != tile.pos)
+                .enumerate()
+                // Opaque tiles are already handled by the shadowcasting
+                .filter(|(_, p)| map.is_in_bounds(*p) && !map.is_opaque(*p))
+                .any(|(i, p)| {
+                    let sightline = eye + (target - eye) * (i + 1) as f32 / steps;
+                    height(p) as f32 > sightline
+                });
+            if !hidden {
+                mark_visible(tile.pos);
+            }
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Compute the fov in a map from the given position, where tiles can be partly
+/// transparent, passing each visible tile to `mark_visible`.
+///
+/// The `opacity` of the tiles between the origin and a tile is added up, and the
+/// tile is visible as long as the total stays below `1.0`. Tiles with an opacity
+/// of `1.0` or more block vision like opaque tiles. The map itself is not
+/// modified.
+///
+/// Opacity is summed over the tiles of a Bresenham line, an approximation of
+/// the sightline that can pass beside a tile the shadowcasting would count. With
+/// a line walked for every visible tile this takes O(tiles × range).
+pub fn compute_translucent<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+    opacity: impl Fn(IVec2) -> f32,
+    mut mark_visible: impl FnMut(IVec2),
+) {
+    let origin = origin.as_ivec2();
+    let mut map = OpaqueIf {
+        map,
+        opaque: |p| opacity(p) >= 1.0,
+    };
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut map,
+        &mut |map, tile| {
+            let total: f32 = Line::new(origin, tile.pos)
+                .take_while(|p| *p != tile.pos)
+                // Opaque tiles are already handled by the shadowcasting
+                .filter(|p| map.is_in_bounds(*p) && !map.is_opaque(*p))
+                .map(&opacity)
+                .sum();
+            if total < 1.0 {
+                mark_visible(tile.pos);
+            }
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Compute the fov in a map from the given position, passing each visible tile
+/// to `mark_visible` along with whether or not it's the origin.
+///
+/// The origin is always reported first, if it's in bounds. The map itself is not
+/// modified.
+pub fn compute_with_origin<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+    mut mark_visible: impl FnMut(IVec2, bool),
+) {
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut ReadOnly(map),
+        &mut |_, tile| {
+            // The origin is the only tile not found in an octant
+            mark_visible(tile.pos, tile.octant == -1);
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Compute the fov in a map from the given position, passing each visible
+/// floor tile to `mark_floor` and each visible opaque tile to `mark_wall`.
+///
+/// Each tile is passed to one of the callbacks exactly once. The map itself is
+/// not modified.
+pub fn compute_walls<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+    mut mark_floor: impl FnMut(IVec2),
+    mut mark_wall: impl FnMut(IVec2),
+) {
+    let mut seen = HashSet::new();
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut ReadOnly(map),
+        &mut |_, tile| {
+            // Tiles along the octant edges are found twice
+            if seen.insert(tile.pos) {
+                if tile.opaque {
+                    mark_wall(tile.pos);
+                } else {
+                    mark_floor(tile.pos);
+                }
+            }
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Compute the fov in a map from the given position, stopping once `max_tiles`
+/// unique tiles have been marked visible.
+///
+/// Returns [ControlFlow::Break] if the computation was stopped by the budget,
+/// meaning another visible tile was found after `max_tiles` had been marked.
+/// If the fov has exactly `max_tiles` tiles it completes normally.
+pub fn compute_budgeted<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    max_tiles: usize,
+    map: &mut T,
+) -> ControlFlow<()> {
+    let mut seen = HashSet::new();
+    compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        map,
+        &mut |map, tile| {
+            if seen.insert(tile.pos) {
+                if seen.len() > max_tiles {
+                    return ControlFlow::Break(());
+                }
+                map.set_visible(tile.pos);
+            }
+            ControlFlow::Continue(())
+        },
+    )
+}
+
+/// Compute the fov in a map from the given position, passing each visible tile
+/// to `mark_visible` and each in range tile that isn't visible to
+/// `mark_shadowed`.
+///
+/// Each tile is passed to one of the callbacks exactly once. The map itself is
+/// not modified.
+///
+/// Finding the shadowed tiles means checking every tile in the square of
+/// `range` around the origin against the visible tiles, whether or not it's in
+/// the map. That's O(range²) no matter how small the map is, so the range should
+/// be kept small - don't pass `i32::MAX` to mean "unlimited" like with
+/// [compute]. The square must also fit inside the range of an `i32`.
+pub fn compute_with_shadows<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+    mut mark_visible: impl FnMut(IVec2),
+    mut mark_shadowed: impl FnMut(IVec2),
+) {
+    let origin = origin.as_ivec2();
+    let mut visible = HashSet::new();
+    compute_unique(origin, range, map, |p| {
+        visible.insert(p);
+        mark_visible(p);
+    });
+
+    for y in -range..=range {
+        for x in -range..=range {
+            let p = origin + IVec2::new(x, y);
+            if map.is_in_bounds(p) && map.in_range(origin, p, range) && !visible.contains(&p) {
+                mark_shadowed(p);
+            }
+        }
+    }
+}
+
+/// Compute the fov in a map from the given position, passing each visible tile
+/// and its distance from the origin to `mark_visible`.
+///
+/// The distance is the same one used to clip the fov to its range, so it can be
+/// used for light attenuation without being recalculated. The map itself is not
+/// modified.
+pub fn compute_attenuated<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+    mut mark_visible: impl FnMut(IVec2, f32),
+) {
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut ReadOnly(map),
+        &mut |_, tile| {
+            mark_visible(tile.pos, tile.dist);
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Compute the fov in a map from the given position, passing each visible tile
+/// and the whole number of tiles of range left over after reaching it to
+/// `mark_visible`.
+///
+/// The range left over is `range` minus the tile's distance from the origin,
+/// rounded down, so the origin gets the full `range`. This can be used to
+/// propagate light by computing the fov again from a visible tile with its
+/// remaining range. The map itself is not modified.
+pub fn compute_remaining<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+    mut mark_visible: impl FnMut(IVec2, i32),
+) {
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut ReadOnly(map),
+        &mut |_, tile| {
+            let remaining = (range as f32 - tile.dist).floor() as i32;
+            mark_visible(tile.pos, remaining.max(0));
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Compute the fov in a map from the given position, passing each visible tile
+/// and its brightness from 0.0 to 1.0 to `mark_visible`.
+///
+/// Brightness is calculated by the given [Falloff] from the same distance used
+/// to clip the fov to its range. The map itself is not modified.
+pub fn compute_lit<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    falloff: Falloff,
+    map: &T,
+    mut mark_visible: impl FnMut(IVec2, f32),
+) {
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut ReadOnly(map),
+        &mut |_, tile| {
+            mark_visible(tile.pos, falloff.brightness(tile.dist, range as f32));
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Compute the fov in a map from the given position, passing each visible tile
+/// and its brightness from 0.0 to 1.0 to `mark_visible`, with separate metrics
+/// for the range and the brightness.
+///
+/// The fov is clipped to its range using `clip_metric`, while brightness is
+/// calculated by the given [Falloff] from the distance measured by
+/// `falloff_metric`. For example a light with a circular edge but diamond
+/// shaped falloff. The map itself is not modified.
+pub fn compute_lit_with_metrics<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    clip_metric: RangeMetric,
+    falloff_metric: RangeMetric,
+    falloff: Falloff,
+    map: &T,
+    mut mark_visible: impl FnMut(IVec2, f32),
+) {
+    let origin = origin.as_ivec2();
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut MetricMap {
+            map: &mut ReadOnly(map),
+            metric: clip_metric,
+            bound: RangeBound::Inclusive,
+        },
+        &mut |_, tile| {
+            let dist = falloff_metric.dist(origin, tile.pos);
+            mark_visible(tile.pos, falloff.brightness(dist, range as f32));
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Compute the fov in a map from the given position, also marking opaque tiles
+/// just beyond the range as visible if they are next to a visible floor.
+///
+/// This avoids a dark rim of unlit walls around the edge of the fov. Floor tiles
+/// are still limited to the range.
+pub fn compute_overscan<T: VisibilityMap>(origin: impl GridPoint, range: i32, map: &mut T) {
+    let origin = origin.as_ivec2();
+    let mut floors = Vec::new();
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        map,
+        &mut |map, tile| {
+            map.set_visible(tile.pos);
+            if !tile.opaque {
+                floors.push(tile.pos);
+            }
+            ControlFlow::Continue(())
+        },
+    );
+    for p in floors {
+        for dir in ADJACENT {
+            let wall = p + dir;
+            if map.is_in_bounds(wall) && map.is_opaque(wall) && !map.in_range(origin, wall, range) {
+                map.set_visible(wall);
+            }
+        }
+    }
+}
+
+/// The offsets to the eight tiles surrounding a tile.
+const ADJACENT: [IVec2; 8] = [
+    IVec2::new(-1, -1),
+    IVec2::new(0, -1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 0),
+    IVec2::new(1, 0),
+    IVec2::new(-1, 1),
+    IVec2::new(0, 1),
+    IVec2::new(1, 1),
+];
+
+/// Compute the fov in a map from the given position out to the last of the
+/// ascending `tiers` radii, passing each visible tile and the index of the
+/// first tier it's within to `mark_visible`.
+///
+/// Tiles are assigned to tiers with [VisibilityMap::in_range], the same check
+/// that clips the fov to its range. Useful for lighting with discrete
+/// brightness bands. The map itself is not modified.
+pub fn compute_tiered<T: VisibilityMap>(
+    origin: impl GridPoint,
+    tiers: &[i32],
+    map: &T,
+    mut mark_visible: impl FnMut(IVec2, usize),
+) {
+    debug_assert!(
+        tiers.windows(2).all(|w| w[0] <= w[1]),
+        "Tiers must be in ascending order"
+    );
+    let range = match tiers.last() {
+        Some(range) => *range,
+        None => return,
+    };
+    let origin = origin.as_ivec2();
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut ReadOnly(map),
+        &mut |_, tile| {
+            if let Some(tier) = tiers
+                .iter()
+                .position(|r| map.in_range(origin, tile.pos, *r))
+            {
+                mark_visible(tile.pos, tier);
+            }
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Compute the fov in a map from several `(origin, range)` sources, passing each
+/// tile visible from any of them to `mark_visible` exactly once.
+///
+/// The map itself is not modified.
+pub fn compute_multi<T: VisibilityMap>(
+    sources: &[(IVec2, i32)],
+    map: &T,
+    mark_visible: impl FnMut(IVec2),
+) {
+    compute_sources(sources.iter().copied(), map, mark_visible);
+}
+
+/// Compute the fov in a map lit by an extended light source, such as a window
+/// or a fire, passing each tile visible from any of the `emitters` to
+/// `mark_visible` exactly once.
+///
+/// Each emitter tile sees up to `range` tiles. The map itself is not modified.
+pub fn compute_area<T: VisibilityMap>(
+    emitters: &[IVec2],
+    range: i32,
+    map: &T,
+    mark_visible: impl FnMut(IVec2),
+) {
+    compute_sources(emitters.iter().map(|&p| (p, range)), map, mark_visible);
+}
+
+/// Passes each tile visible from any of the `(origin, range)` sources to
+/// `mark_visible` exactly once.
+fn compute_sources<T: VisibilityMap>(
+    sources: impl IntoIterator<Item = (IVec2, i32)>,
+    map: &T,
+    mut mark_visible: impl FnMut(IVec2),
+) {
+    let mut sections = Vec::new();
+    let mut seen = HashSet::new();
+    for (origin, range) in sources {
+        let _ = compute_range_in(
+            Params::new(origin, Range::Radius(range)),
+            &mut ReadOnly(map),
+            &mut sections,
+            &mut |_, tile| {
+                if seen.insert(tile.pos) {
+                    mark_visible(tile.pos);
+                }
+                ControlFlow::Continue(())
+            },
+        );
+    }
+}
+
+/// Compute the fov in a map from several `(origin, range)` sources in parallel,
+/// writing every tile visible from any of them into a separate grid.
+///
+/// `visible` is cleared first. Tiles outside of its bounds are ignored. The map
+/// itself is not modified.
+#[cfg(feature = "rayon")]
+pub fn compute_multi_par<T: VisibilityMap + Sync>(
+    sources: &[(IVec2, i32)],
+    map: &T,
+    visible: &mut Grid<bool>,
+) {
+    use rayon::prelude::*;
+
+    let tiles: Vec<Vec<IVec2>> = sources
+        .par_iter()
+        .map(|&(origin, range)| compute_tiles(origin, range, map))
+        .collect();
+
+    visible.iter_mut().for_each(|v| *v = false);
+    for p in tiles.into_iter().flatten() {
+        if visible.in_bounds(p) {
+            visible[p] = true;
+        }
+    }
+}
+
+/// Compute the fov in a map from several `(origin, range)` light sources,
+/// passing each lit tile and the brightest light reaching it to `mark_visible`.
+///
+/// Each tile is reported exactly once, after every source has been computed, in
+/// no particular order. The map itself is not modified.
+pub fn compute_multi_lit<T: VisibilityMap>(
+    sources: &[(IVec2, i32)],
+    falloff: Falloff,
+    map: &T,
+    mark_visible: impl FnMut(IVec2, f32),
+) {
+    compute_multi_blended(sources, falloff, Blend::Max, map, mark_visible);
+}
+
+/// Compute the fov in a map from several `(origin, range)` light sources,
+/// passing each lit tile and the light reaching it, combined by the given
+/// [Blend], to `mark_visible`.
+///
+/// Each source lights a tile at most once. [Blend::Additive] can give values
+/// above 1.0, which are left for the caller to clamp. Each tile is reported
+/// exactly once, after every source has been computed, in no particular order.
+/// The map itself is not modified.
+pub fn compute_multi_blended<T: VisibilityMap>(
+    sources: &[(IVec2, i32)],
+    falloff: Falloff,
+    blend: Blend,
+    map: &T,
+    mut mark_visible: impl FnMut(IVec2, f32),
+) {
+    let mut sections = Vec::new();
+    let mut seen = HashSet::new();
+    let mut lit: HashMap<IVec2, f32> = HashMap::new();
+    for &(origin, range) in sources {
+        seen.clear();
+        let _ = compute_range_in(
+            Params::new(origin, Range::Radius(range)),
+            &mut ReadOnly(map),
+            &mut sections,
+            &mut |_, tile| {
+                // Tiles along the octant edges are found twice
+                if seen.insert(tile.pos) {
+                    let brightness = falloff.brightness(tile.dist, range as f32);
+                    lit.entry(tile.pos)
+                        .and_modify(|v| *v = blend.blend(*v, brightness))
+                        .or_insert(brightness);
+                }
+                ControlFlow::Continue(())
+            },
+        );
+    }
+    for (p, brightness) in lit {
+        mark_visible(p, brightness);
+    }
+}
+
+/// Compute the fov in a map from the given position, passing each visible tile
+/// and its angle from the origin to `mark_visible`.
+///
+/// The angle is in radians from -PI to PI, measured counter-clockwise from the
+/// positive x axis. The origin has no direction and is reported with an angle of
+/// 0.0. The map itself is not modified.
+pub fn compute_angles<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+    mut mark_visible: impl FnMut(IVec2, f32),
+) {
+    let origin = origin.as_ivec2();
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut ReadOnly(map),
+        &mut |_, tile| {
+            let d = (tile.pos - origin).as_vec2();
+            mark_visible(tile.pos, d.y.atan2(d.x));
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Compute the fov in a map from the given position, passing each visible tile
+/// and the octant (0-7) it was found in to `mark_visible`.
+///
+/// The origin is reported once with an octant of -1. A tile on the boundary
+/// between two octants may be reported once from each of them. The map itself is
+/// not modified.
+pub fn compute_debug<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+    mut mark_visible: impl FnMut(IVec2, i32),
+) {
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut ReadOnly(map),
+        &mut |_, tile| {
+            mark_visible(tile.pos, tile.octant);
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Compute the fov in a map from the given position, passing each visible tile,
+/// its octant-local position and the octant (0-7) it was found in to
+/// `mark_visible`.
+///
+/// In an octant's local space `x` is the distance along the octant's axis and
+/// `y` the distance from the axis towards the diagonal, so `0 <= y <= x`. See
+/// [compute_octants] for the direction each octant covers. The origin is
+/// reported once with a local position of `[0,0]` and an octant of -1. The map
+/// itself is not modified.
+pub fn compute_debug_local<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+    mut mark_visible: impl FnMut(IVec2, IVec2, i32),
+) {
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        &mut ReadOnly(map),
+        &mut |_, tile| {
+            mark_visible(tile.pos, tile.local, tile.octant);
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// A combination of fov settings, built up with chained setters.
+///
+/// ```rust
+/// use adam_fov_rs::*;
+///
+/// let mut map = VisibilityMap2d::default([50, 50]);
+/// fov::FovConfig::new(8)
+///     .metric(RangeMetric::Chebyshev)
+///     .cone([1, 0], 45.0)
+///     .min_range(2)
+///     .compute([15, 15], &mut map);
+/// ```
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct FovConfig {
+    range: i32,
+    min_range: i32,
+    metric: Option<RangeMetric>,
+    cone: Option<(Vec2, f32)>,
+    symmetry: Symmetry,
+    diagonal: DiagonalRule,
+    exclude_origin: bool,
+    corner_peek: bool,
+    opaque_origin: OpaqueOrigin,
+}
+
+impl FovConfig {
+    pub fn new(range: i32) -> Self {
+        Self {
+            range,
+            ..Default::default()
+        }
+    }
+
+    /// The maximum distance from the origin that tiles can be seen.
+    pub fn range(mut self, range: i32) -> Self {
+        self.range = range;
+        self
+    }
+
+    /// Tiles closer to the origin than `min_range` are not marked visible, but
+    /// can still block vision.
+    pub fn min_range(mut self, min_range: i32) -> Self {
+        self.min_range = min_range;
+        self
+    }
+
+    /// Measure distance with the given metric instead of the map's
+    /// [VisibilityMap::dist].
+    pub fn metric(mut self, metric: RangeMetric) -> Self {
+        self.metric = Some(metric);
+        self
+    }
+
+    /// Limit the fov to a cone facing the given direction. See [compute_cone].
+    ///
+    /// A `facing` of `[0,0]` covers the full circle.
+    pub fn cone(mut self, facing: impl GridPoint, half_angle_degrees: f32) -> Self {
+        self.cone = Some((facing.as_vec2(), half_angle_degrees));
+        self
+    }
+
+    pub fn symmetry(mut self, symmetry: Symmetry) -> Self {
+        self.symmetry = symmetry;
+        self
+    }
+
+    pub fn diagonal_rule(mut self, diagonal: DiagonalRule) -> Self {
+        self.diagonal = diagonal;
+        self
+    }
+
+    /// Whether the origin itself is marked visible. Defaults to true.
+    ///
+    /// This applies regardless of the range or whether the origin is opaque.
+    #[doc(alias = "see_self")]
+    pub fn include_origin(mut self, include_origin: bool) -> Self {
+        self.exclude_origin = !include_origin;
+        self
+    }
+
+    /// Whether the origin can peek around the corner of a wall diagonally next
+    /// to it. Defaults to false.
+    ///
+    /// When enabled, if a wall touches the origin's corner and the open tiles
+    /// beside it continue past the wall, the tile diagonally past the wall's
+    /// corner is also marked visible.
+    pub fn corner_peek(mut self, corner_peek: bool) -> Self {
+        self.corner_peek = corner_peek;
+        self
+    }
+
+    /// What the fov sees when the origin is opaque. Defaults to
+    /// [OpaqueOrigin::OnlySelf].
+    pub fn opaque_origin(mut self, opaque_origin: OpaqueOrigin) -> Self {
+        self.opaque_origin = opaque_origin;
+        self
+    }
+
+    /// Compute the fov in a map from the given position and mark visible tiles
+    /// on the map.
+    pub fn compute<T: VisibilityMap>(&self, origin: impl GridPoint, map: &mut T) {
+        let origin = origin.as_ivec2();
+        match self.metric {
+            Some(metric) => {
+                let mut map = MetricMap {
+                    map,
+                    metric,
+                    bound: RangeBound::Inclusive,
+                };
+                self.compute_in(origin, &mut map, &mut |map, p| map.set_visible(p))
+            }
+            None => self.compute_in(origin, map, &mut |map, p| map.set_visible(p)),
+        }
+    }
+
+    /// Compute the fov in a map from the given position, passing each visible
+    /// tile to `mark_visible`.
+    ///
+    /// The map itself is not modified.
+    pub fn compute_with<T: VisibilityMap>(
+        &self,
+        origin: impl GridPoint,
+        map: &T,
+        mut mark_visible: impl FnMut(IVec2),
+    ) {
+        let origin = origin.as_ivec2();
+        let mut map = ReadOnly(map);
+        match self.metric {
+            Some(metric) => self.compute_in(
+                origin,
+                &mut MetricMap {
+                    map: &mut map,
+                    metric,
+                    bound: RangeBound::Inclusive,
+                },
+                &mut |_, p| mark_visible(p),
+            ),
+            None => self.compute_in(origin, &mut map, &mut |_, p| mark_visible(p)),
+        }
+    }
+
+    fn compute_in<T: VisibilityMap>(
+        &self,
+        origin: IVec2,
+        map: &mut T,
+        mark: &mut impl FnMut(&mut T, IVec2),
+    ) {
+        let params = Params {
+            symmetry: self.symmetry,
+            diagonal: self.diagonal,
+            include_origin: !self.exclude_origin,
+            opaque_origin: self.opaque_origin,
+            ..Params::new(origin, Range::Radius(self.range))
+        };
+        let cone = self
+            .cone
+            .map(|(facing, angle)| (facing, angle.to_radians().cos()));
+        let mut mark_tile = |map: &mut T, pos: IVec2, dist: f32| {
+            let in_range = dist >= self.min_range as f32;
+            let in_cone = match cone {
+                Some((facing, min_cos)) => {
+                    pos == origin || in_cone((pos - origin).as_vec2(), facing, min_cos)
+                }
+                None => true,
+            };
+            if in_range && in_cone {
+                mark(map, pos);
+            }
+        };
+        let _ = compute_range_with(params, map, &mut |map, tile| {
+            mark_tile(map, tile.pos, tile.dist);
+            ControlFlow::Continue(())
+        });
+
+        if self.corner_peek && !params.origin_blocked(map) {
+            for p in corner_peeks(origin, map) {
+                if map.is_in_bounds(p) && map.in_range(origin, p, self.range) {
+                    let dist = map.dist(origin, p);
+                    mark_tile(map, p, dist);
+                }
+            }
+        }
+    }
+}
+
+/// The tiles diagonally past any wall corners touching the origin's corners.
+///
+/// A wall diagonal to the origin is a corner if the open tiles beside the origin
+/// continue past it along a row or column.
+fn corner_peeks<T: VisibilityMap>(origin: IVec2, map: &T) -> Vec<IVec2> {
+    let open = |p: IVec2| map.is_in_bounds(p) && !map.is_opaque(p);
+    let mut peeks = Vec::new();
+    for d in [
+        IVec2::new(1, 1),
+        IVec2::new(-1, 1),
+        IVec2::new(-1, -1),
+        IVec2::new(1, -1),
+    ] {
+        let corner = origin + d;
+        if open(corner) {
+            continue;
+        }
+        let (dx, dy) = (IVec2::new(d.x, 0), IVec2::new(0, d.y));
+        if (open(origin + dx) && open(corner + dx)) || (open(origin + dy) && open(corner + dy)) {
+            peeks.push(corner + d);
+        }
+    }
+    peeks
+}
+
+/// Reusable storage for computing the fov repeatedly without reallocating.
+///
+/// Like [compute_unique], each visible tile is only marked once per call.
+#[derive(Default)]
+pub struct Fov {
+    sections: Vec<Section>,
+    seen: HashSet<IVec2>,
+}
+
+impl Fov {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compute the fov in a map from the given position, passing each visible
+    /// tile to `mark_visible` exactly once.
+    ///
+    /// The map itself is not modified.
+    pub fn compute<T: VisibilityMap>(
+        &mut self,
+        origin: impl GridPoint,
+        range: i32,
+        map: &T,
+        mut mark_visible: impl FnMut(IVec2),
+    ) {
+        let seen = &mut self.seen;
+        seen.clear();
+        let _ = compute_range_in(
+            Params::new(origin, Range::Radius(range)),
+            &mut ReadOnly(map),
+            &mut self.sections,
+            &mut |_, tile| {
+                if seen.insert(tile.pos) {
+                    mark_visible(tile.pos);
+                }
+                ControlFlow::Continue(())
+            },
+        );
+    }
+}
+
+/// Returns an iterator that lazily computes the fov in a map from the given
+/// position, yielding each visible tile once.
+///
+/// The map itself is not modified, and only needs to be borrowed immutably, so
+/// the tiles can be collected or zipped with other data without the borrow
+/// problems of a callback.
+///
+/// ```rust
+/// use adam_fov_rs::*;
+///
+/// let map = VisibilityMap2d::default([30, 30]);
+/// let visible: Vec<IVec2> = fov::iter([15, 15], 5, &map).collect();
+/// assert!(visible.contains(&IVec2::new(15, 20)));
+/// ```
+pub fn iter<T: VisibilityMap>(origin: impl GridPoint, range: i32, map: &T) -> FovIter<'_, T> {
+    let origin = origin.as_ivec2();
+    let mut tiles = Vec::new();
+    if map.is_in_bounds(origin) {
+        tiles.push((origin, 0.0));
+    }
+    let params = Params::new(origin, Range::Radius(range));
+    let sections = match params.origin_blocked(map) {
+        true => Vec::new(),
+        false => Section::octants().collect(),
+    };
+    FovIter {
+        map,
+        params,
+        sections,
+        current: None,
+        tiles,
+        seen: HashSet::new(),
+    }
+}
+
+/// Returns an iterator that lazily computes the fov in a map from the given
+/// position, yielding each visible tile once along with its distance from the
+/// origin.
+///
+/// The distance is the same one used to clip the fov to its range. The map
+/// itself is not modified.
+pub fn iter_with_dist<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &T,
+) -> FovDistIter<'_, T> {
+    FovDistIter(iter(origin, range, map))
+}
+
+/// An iterator over the visible tiles of an fov, computed one column at a time.
+///
+/// Created by [iter].
+pub struct FovIter<'a, T> {
+    map: &'a T,
+    params: Params,
+    sections: Vec<Section>,
+    current: Option<Section>,
+    tiles: Vec<(IVec2, f32)>,
+    seen: HashSet<IVec2>,
+}
+
+impl<'a, T: VisibilityMap> FovIter<'a, T> {
+    fn next_with_dist(&mut self) -> Option<(IVec2, f32)> {
+        loop {
+            while let Some((p, dist)) = self.tiles.pop() {
+                if self.seen.insert(p) {
+                    return Some((p, dist));
+                }
+            }
+
+            let mut section = match self.current.take() {
+                Some(section) => section,
+                None => self.sections.pop()?,
+            };
+
+            let tiles = &mut self.tiles;
+            if let ControlFlow::Continue(true) = compute_column(
+                &mut section,
+                &self.params,
+                &mut ReadOnly(self.map),
+                &mut self.sections,
+                &mut |_, tile| {
+                    tiles.push((tile.pos, tile.dist));
+                    ControlFlow::Continue(())
+                },
+            ) {
+                self.current = Some(section);
+            }
+        }
+    }
+}
+
+impl<'a, T: VisibilityMap> Iterator for FovIter<'a, T> {
+    type Item = IVec2;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_dist().map(|(p, _)| p)
+    }
+}
+
+/// An iterator over the visible tiles of an fov and their distance from the
+/// origin, computed one column at a time.
+///
+/// Created by [iter_with_dist].
+pub struct FovDistIter<'a, T>(FovIter<'a, T>);
+
+impl<'a, T: VisibilityMap> Iterator for FovDistIter<'a, T> {
+    type Item = (IVec2, f32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_with_dist()
+    }
+}
+
+/// Returns true if `b` is visible from `a`.
+///
+/// This uses the same rules as [compute], so when neither tile is opaque it
+/// agrees with the result of an fov computed from `a` with enough range to
+/// reach `b`. Only the octants containing `b` are computed, and computation
+/// stops as soon as `b` is reached.
+///
+/// Line of sight between two tiles inside the map is symmetric -
+/// `line_of_sight(a, b)` is always equal to `line_of_sight(b, a)`. The fov
+/// itself is only symmetric between non-opaque tiles: with [Symmetry::Balanced]
+/// an opaque tile is visible if any part of it can be seen, so a floor tile can
+/// see a wall that can't see it back. When either tile is opaque and the fov
+/// from `a` doesn't reach `b`, a second fov is computed from `b` and line of
+/// sight holds if it reaches `a`. That fallback is what makes the result
+/// symmetric, and it can double the cost of a failed check involving a wall.
+pub fn line_of_sight<T: VisibilityMap>(a: impl GridPoint, b: impl GridPoint, map: &T) -> bool {
+    let (a, b) = (a.as_ivec2(), b.as_ivec2());
+    if a == b {
+        return true;
+    }
+
+    let d = (b - a).abs();
+    let reaches_from =
+        |a: IVec2, b: IVec2| reaches(Params::new(a, Range::Columns(d.max_element())), b, map);
+    let opaque = |p: IVec2| map.is_in_bounds(p) && map.is_opaque(p);
+    reaches_from(a, b) || ((opaque(a) || opaque(b)) && reaches_from(b, a))
+}
+
+/// Whether `target` would be marked visible by [compute] from `origin` with the
+/// given range.
+///
+/// Only the octants containing `target` are computed, and computation stops as
+/// soon as it's reached, so this is much faster than computing the whole fov.
+pub fn is_visible_from<T: VisibilityMap>(
+    origin: impl GridPoint,
+    target: impl GridPoint,
+    range: i32,
+    map: &T,
+) -> bool {
+    let (origin, target) = (origin.as_ivec2(), target.as_ivec2());
+    if origin == target {
+        return map.is_in_bounds(origin);
+    }
+    reaches(Params::new(origin, Range::Radius(range)), target, map)
+}
+
+/// Whether `target` would be marked visible by [compute_with_metric] from
+/// `origin` with the given range and metric.
+///
+/// Use this to check a single tile against a fov clipped by a [RangeMetric]
+/// other than the map's own, such as square vision with
+/// [RangeMetric::Chebyshev].
+pub fn is_visible_with_metric<T: VisibilityMap>(
+    origin: impl GridPoint,
+    target: impl GridPoint,
+    range: i32,
+    metric: RangeMetric,
+    map: &T,
+) -> bool {
+    let map = MetricMap {
+        map: &mut ReadOnly(map),
+        metric,
+        bound: RangeBound::Inclusive,
+    };
+    is_visible_from(origin, target, range, &map)
+}
+
+/// Compute the octants containing `b` until it's marked visible.
+fn reaches<T: VisibilityMap>(params: Params, b: IVec2, map: &T) -> bool {
+    let a = params.origin;
+    if params.origin_blocked(map) {
+        // Nothing past the origin's neighbours needs computing
+        let mut found = |_: &mut _, tile: Tile| match tile.pos == b {
+            true => ControlFlow::Break(()),
+            false => ControlFlow::Continue(()),
+        };
+        return compute_range_with(params, &mut ReadOnly(map), &mut found).is_break();
+    }
+    let d = (b - a).abs();
+    let local = IVec2::new(d.max_element(), d.min_element());
+
+    let mut sections: Vec<_> = Section::octants()
+        .filter(|s| transform(local.x, local.y, s.octant, a) == b)
+        .collect();
+    let mut map = ReadOnly(map);
+    let mut mark = |_: &mut _, tile: Tile| {
+        if tile.pos == b {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    };
+
+    while let Some(mut section) = sections.pop() {
+        loop {
+            match compute_column(&mut section, &params, &mut map, &mut sections, &mut mark) {
+                ControlFlow::Break(()) => return true,
+                ControlFlow::Continue(false) => break,
+                ControlFlow::Continue(true) => {}
+            }
+        }
+    }
+    false
+}
+
+/// Cast a ray in a straight line from `origin` to `target` and return the first
+/// tile that blocks light, or `None` if the target was reached unobstructed.
+///
+/// A tile blocks the ray in the same way it would block the fov - if it's opaque
+/// or out of bounds. The origin is never considered, but the target is.
+pub fn cast_ray<T: VisibilityMap>(
+    origin: impl GridPoint,
+    target: impl GridPoint,
+    map: &T,
+) -> Option<IVec2> {
+    Line::new(origin.as_ivec2(), target.as_ivec2())
+        .find(|p| !map.is_in_bounds(*p) || map.is_opaque(*p))
+}
+
+/// Returns how many tiles can be seen along each of the eight compass
+/// directions from `origin` before vision is blocked or runs out of range.
+///
+/// The directions are ordered clockwise starting from east, with +y as north:
+/// east, southeast, south, southwest, west, northwest, north, northeast. A
+/// direction that starts with an opaque or out of bounds tile has a range of 0.
+pub fn sight_ranges<T: VisibilityMap>(origin: impl GridPoint, range: i32, map: &T) -> [i32; 8] {
+    let origin = origin.as_ivec2();
+    [
+        IVec2::new(1, 0),
+        IVec2::new(1, -1),
+        IVec2::new(0, -1),
+        IVec2::new(-1, -1),
+        IVec2::new(-1, 0),
+        IVec2::new(-1, 1),
+        IVec2::new(0, 1),
+        IVec2::new(1, 1),
+    ]
+    .map(|dir| {
+        (1..=range.max(0))
+            .map(|i| origin + dir * i)
+            .take_while(|p| {
+                map.is_in_bounds(*p) && !map.is_opaque(*p) && map.in_range(origin, *p, range)
+            })
+            .count() as i32
+    })
+}
+
+/// Compute the fov in a map from the given position, but only mark tiles
+/// that are at least `min_range` away from the origin as visible.
+///
+/// Tiles inside `min_range` still block light as normal.
+pub fn compute_ring<T: VisibilityMap>(
+    origin: impl GridPoint,
+    min_range: i32,
+    max_range: i32,
+    map: &mut T,
+) {
+    let origin = origin.as_ivec2();
+    compute(
+        origin,
+        max_range,
+        &mut RingMap {
+            map,
+            origin,
+            min_range,
+        },
+    );
+}
+
+/// Compute the fov in a map from the given position, only marking tiles inside
+/// a cone pointing in the `facing` direction.
+///
+/// A tile is inside the cone if the angle between `facing` and the direction
+/// from the origin to the tile is at most `half_angle_degrees`. Tiles outside
+/// the cone still block light as normal. The origin is always visible.
+///
+/// A `facing` of `[0,0]` has no direction, so the cone covers the full circle.
+pub fn compute_cone<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    facing: impl GridPoint,
+    half_angle_degrees: f32,
+    map: &mut T,
+) {
+    compute_cone_towards(
+        origin.as_ivec2(),
+        range,
+        facing.as_vec2(),
+        half_angle_degrees,
+        map,
+    );
+}
+
+/// Compute the fov in a map from the given position, only marking tiles inside
+/// a cone facing the given angle.
+///
+/// `facing_degrees` is measured counter-clockwise from +x, with +y at 90
+/// degrees, and can be any value - angles outside of 0-360 wrap around. The
+/// cone is checked by the exact angle to each tile rather than by octant, so it
+/// turns smoothly as the angle changes, and a `half_angle_degrees` over 90
+/// gives a cone wider than a half circle. See [compute_cone].
+pub fn compute_cone_angle<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    facing_degrees: f32,
+    half_angle_degrees: f32,
+    map: &mut T,
+) {
+    let (sin, cos) = facing_degrees.to_radians().sin_cos();
+    compute_cone_towards(
+        origin.as_ivec2(),
+        range,
+        Vec2::new(cos, sin),
+        half_angle_degrees,
+        map,
+    );
+}
+
+fn compute_cone_towards<T: VisibilityMap>(
+    origin: IVec2,
+    range: i32,
+    facing: Vec2,
+    half_angle_degrees: f32,
+    map: &mut T,
+) {
+    let min_cos = half_angle_degrees.to_radians().cos();
+
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        map,
+        &mut |map, tile| {
+            let dir = (tile.pos - origin).as_vec2();
+            if tile.pos == origin || in_cone(dir, facing, min_cos) {
+                map.set_visible(tile.pos);
+            }
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Compute the fov in a map from the given position, only marking tiles in a
+/// straight line along each of the four cardinal directions.
+///
+/// This gives a plus shaped fov that doesn't spread diagonally, which suits
+/// maps made of orthogonal corridors.
+pub fn compute_cardinal<T: VisibilityMap>(origin: impl GridPoint, range: i32, map: &mut T) {
+    let origin = origin.as_ivec2();
+    let _ = compute_range_with(
+        Params::new(origin, Range::Radius(range)),
+        map,
+        &mut |map, tile| {
+            if tile.pos.x == origin.x || tile.pos.y == origin.y {
+                map.set_visible(tile.pos);
+            }
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+/// Whether the angle between `dir` and `facing` is small enough that its
+/// cosine is at least `min_cos`.
+///
+/// A zero `facing` has no angle to any tile, so every tile is inside the cone.
+fn in_cone(dir: Vec2, facing: Vec2, min_cos: f32) -> bool {
+    if facing == Vec2::ZERO {
+        return true;
+    }
+    // Allow for rounding errors on the edges of the cone
+    dir.dot(facing) / (dir.length() * facing.length()) >= min_cos - 0.0001
+}
+
+/// Compute the fov in a map from the given position, only computing the given
+/// octants.
+///
+/// Each octant is a 45 degree wedge starting on one of the axes. With +y
+/// pointing north the octants are:
+///
+/// | Octant | Direction | Axis |
+/// |--------|-----------|------|
+/// | 0 | East-southeast | +x, towards -y |
+/// | 1 | South-southeast | -y, towards +x |
+/// | 2 | South-southwest | -y, towards -x |
+/// | 3 | West-southwest | -x, towards -y |
+/// | 4 | West-northwest | -x, towards +y |
+/// | 5 | North-northwest | +y, towards -x |
+/// | 6 | North-northeast | +y, towards +x |
+/// | 7 | East-northeast | +x, towards +y |
+///
+/// Octants can be given as numbers or as an [Octant](crate::Octant). Octants outside of 0-7
+/// are ignored. The origin is always visible.
+pub fn compute_octants<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    octants: &[impl Into<i32> + Copy],
+    map: &mut T,
+) {
+    let octants = octants
+        .iter()
+        .map(|o| (*o).into())
+        .filter(|o| (0..8).contains(o))
+        .fold(0, |mask, o| mask | 1 << o);
+    compute_range(
+        Params {
+            octants,
+            ..Params::new(origin, Range::Radius(range))
+        },
+        map,
+    );
+}
+
+/// Compute the fov in a map from the given position, with a separate range
+/// for each octant.
+///
+/// `ranges` is indexed by octant, see [compute_octants] for the direction each
+/// one covers. An octant with a range of 0 or less sees nothing. The origin is
+/// always visible.
+pub fn compute_per_octant<T: VisibilityMap>(origin: impl GridPoint, ranges: [i32; 8], map: &mut T) {
+    compute_range(Params::new(origin, Range::PerOctant(ranges)), map);
+}
+
+/// Compute the fov in a map from the given position one octant at a time,
+/// calling `on_octant_start` and `on_octant_end` with each octant (0-7) before
+/// and after it's computed.
+///
+/// The origin is marked before the first octant starts. See [compute_octants]
+/// for the direction each octant covers.
+pub fn compute_with_octant_hooks<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    map: &mut T,
+    mut on_octant_start: impl FnMut(i32),
+    mut on_octant_end: impl FnMut(i32),
+) {
+    let origin = origin.as_ivec2();
+    if map.is_in_bounds(origin) {
+        map.set_visible(origin);
+    }
+    let mut params = Params {
+        include_origin: false,
+        ..Params::new(origin, Range::Radius(range))
+    };
+    let mut sections = Vec::new();
+    for octant in 0..8 {
+        on_octant_start(octant);
+        params.octants = 1 << octant;
+        let _ = compute_range_in(params, map, &mut sections, &mut mark_map);
+        on_octant_end(octant);
+    }
+}
+
+/// Compute the fov in a map from the given position with the given [Symmetry].
+pub fn compute_with_symmetry<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    symmetry: Symmetry,
+    map: &mut T,
+) {
+    compute_range(
+        Params {
+            symmetry,
+            ..Params::new(origin, Range::Radius(range))
+        },
+        map,
+    );
+}
+
+/// Compute the fov in a map from the given position, using the given
+/// [DiagonalRule] to decide whether vision passes between diagonal walls.
+pub fn compute_with_diagonal_rule<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    diagonal: DiagonalRule,
+    map: &mut T,
+) {
+    compute_range(
+        Params {
+            diagonal,
+            ..Params::new(origin, Range::Radius(range))
+        },
+        map,
+    );
+}
+
+/// Compute the fov in a map from the given position, limited to the region where
+/// `in_bounds` returns true.
+///
+/// This allows for irregularly shaped maps. Positions outside the region block
+/// vision just like positions outside the map.
+pub fn compute_bounded_by<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    in_bounds: impl Fn(IVec2) -> bool,
+    map: &mut T,
+) {
+    compute(origin, range, &mut BoundedBy { map, in_bounds });
+}
+
+/// Compute the fov in a map from the given position without marking the origin
+/// itself as visible.
+pub fn compute_without_origin<T: VisibilityMap>(origin: impl GridPoint, range: i32, map: &mut T) {
+    compute_range(
+        Params {
+            include_origin: false,
+            ..Params::new(origin, Range::Radius(range))
+        },
+        map,
+    );
+}
+
+/// Compute the fov in a hex map from the given position, where the map uses
+/// axial `(q, r)` coordinates.
+///
+/// The hexes are laid out as an offset square grid with odd rows shifted right
+/// and the fov is computed on that, so occlusion is only an approximation of
+/// true hex shadows. `range` is measured in hex steps.
+pub fn compute_hex<T: VisibilityMap>(origin: impl GridPoint, range: i32, map: &mut T) {
+    let origin = origin.as_ivec2();
+    let _ = compute_range_with(
+        Params::new(axial_to_offset(origin), Range::Columns(range)),
+        &mut HexMap(map),
+        &mut |map, tile| {
+            if hex_dist(origin, offset_to_axial(tile.pos)) <= range {
+                map.set_visible(tile.pos);
+            }
+            ControlFlow::Continue(())
+        },
+    );
+}
+
+fn axial_to_offset(p: IVec2) -> IVec2 {
+    IVec2::new(p.x + (p.y - (p.y & 1)) / 2, p.y)
+}
+
+fn offset_to_axial(p: IVec2) -> IVec2 {
+    IVec2::new(p.x - (p.y - (p.y & 1)) / 2, p.y)
+}
+
+/// The number of hex steps between two axial coordinates.
+fn hex_dist(a: IVec2, b: IVec2) -> i32 {
+    let d = b - a;
+    (d.x.abs() + (d.x + d.y).abs() + d.y.abs()) / 2
+}
+
+/// Compute the fov in a map from the given position, using the given
+/// [OutOfBounds] rule to decide whether tiles outside the map block vision.
+pub fn compute_with_out_of_bounds<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    out_of_bounds: OutOfBounds,
+    map: &mut T,
+) {
+    match out_of_bounds {
+        OutOfBounds::Opaque => compute(origin, range, map),
+        OutOfBounds::Transparent => compute(origin, range, &mut TransparentEdges(map)),
+    }
+}
+
+/// Compute the fov in a map from the given position, ignoring the map's bounds.
+///
+/// [VisibilityMap::is_in_bounds] is never called, the fov is only limited by
+/// `range`. This suits infinite or chunked maps where [VisibilityMap::is_opaque]
+/// and [VisibilityMap::set_visible] can handle any position.
+pub fn compute_unbounded<T: VisibilityMap>(origin: impl GridPoint, range: i32, map: &mut T) {
+    compute(origin, range, &mut Unbounded(map));
+}
+
+/// Compute the fov in a map of the given size from the given position, wrapping
+/// around the edges of the map.
+///
+/// Positions outside the map are wrapped back into it before being checked for
+/// opacity or marked visible, so the map is never considered out of bounds.
+///
+/// The range is capped to less than half of the map's shortest side, so the fov
+/// can't wrap all the way around and see itself from the other side.
+///
+/// # Panics
+///
+/// Panics if either side of `size` is not positive.
+pub fn compute_wrapped<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    size: impl GridPoint,
+    map: &mut T,
+) {
+    let size = size.as_ivec2();
+    assert!(size.min_element() > 0, "Wrapped map size must be positive");
+    let range = range.min((size.min_element() - 1) / 2);
+    compute(origin, range, &mut WrapMap { map, size });
+}
+
+/// Compute the fov in a map from the given position, clipping the fov
+/// to its range with the given [RangeMetric] instead of [VisibilityMap::dist].
+///
+/// [RangeMetric::Manhattan] can be used for a diamond shaped fov.
+pub fn compute_with_metric<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    metric: RangeMetric,
+    map: &mut T,
+) {
+    compute_with_bound(origin, range, metric, RangeBound::Inclusive, map);
+}
+
+/// Compute the fov in a map from the given position, clipping the fov to its
+/// range with the given [RangeMetric] and [RangeBound].
+///
+/// The range check is done exactly in integers, so the tiles on the edge of the
+/// range are always included or excluded as chosen.
+pub fn compute_with_bound<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    metric: RangeMetric,
+    bound: RangeBound,
+    map: &mut T,
+) {
+    compute(origin, range, &mut MetricMap { map, metric, bound });
+}
+
+/// Overrides a map's distance function with a [RangeMetric].
+struct MetricMap<'a, T> {
+    map: &'a mut T,
+    metric: RangeMetric,
+    bound: RangeBound,
+}
+
+impl<'a, T: VisibilityMap> VisibilityMap for MetricMap<'a, T> {
+    fn is_opaque(&self, p: impl GridPoint) -> bool {
+        self.map.is_opaque(p)
+    }
+
+    fn is_in_bounds(&self, p: impl GridPoint) -> bool {
+        self.map.is_in_bounds(p)
+    }
+
+    fn set_visible(&mut self, p: impl GridPoint) {
+        self.map.set_visible(p)
+    }
+
+    fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+        self.metric.dist(a, b)
+    }
+
+    fn in_range(&self, a: impl GridPoint, b: impl GridPoint, range: i32) -> bool {
+        self.metric.in_range_bounded(a, b, range, self.bound)
+    }
+}
+
+/// A map of the given size that reads opaque tiles from a closure.
+struct SizedMap<F> {
+    size: IVec2,
+    is_opaque: F,
+}
+
+impl<F: Fn(IVec2) -> bool> VisibilityMap for SizedMap<F> {
+    fn is_opaque(&self, p: impl GridPoint) -> bool {
+        (self.is_opaque)(p.as_ivec2())
+    }
+
+    fn is_in_bounds(&self, p: impl GridPoint) -> bool {
+        let p = p.as_ivec2();
+        p.x >= 0 && p.y >= 0 && p.x < self.size.x && p.y < self.size.y
+    }
+
+    fn set_visible(&mut self, _: impl GridPoint) {}
+
+    fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+        RangeMetric::Euclidean.dist(a, b)
+    }
+
+    fn in_range(&self, a: impl GridPoint, b: impl GridPoint, range: i32) -> bool {
+        RangeMetric::Euclidean.in_range(a, b, range)
+    }
+}
+
+/// Reads from a map as if none of its tiles were opaque, without modifying it.
+struct SeeThrough<'a, T>(&'a T);
+
+impl<'a, T: VisibilityMap> VisibilityMap for SeeThrough<'a, T> {
+    fn is_opaque(&self, _: impl GridPoint) -> bool {
+        false
+    }
+
+    fn is_in_bounds(&self, p: impl GridPoint) -> bool {
+        self.0.is_in_bounds(p)
+    }
+
+    fn set_visible(&mut self, _: impl GridPoint) {}
+
+    fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+        self.0.dist(a, b)
+    }
+
+    fn in_range(&self, a: impl GridPoint, b: impl GridPoint, range: i32) -> bool {
+        self.0.in_range(a, b, range)
+    }
+}
+
+/// Reads from a map without ever modifying it.
+struct ReadOnly<'a, T>(&'a T);
+
+impl<'a, T: VisibilityMap> VisibilityMap for ReadOnly<'a, T> {
+    fn is_opaque(&self, p: impl GridPoint) -> bool {
+        self.0.is_opaque(p)
+    }
+
+    fn is_in_bounds(&self, p: impl GridPoint) -> bool {
+        self.0.is_in_bounds(p)
+    }
+
+    fn set_visible(&mut self, _: impl GridPoint) {}
+
+    fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+        self.0.dist(a, b)
+    }
+
+    fn in_range(&self, a: impl GridPoint, b: impl GridPoint, range: i32) -> bool {
+        self.0.in_range(a, b, range)
+    }
+}
+
+/// Collects visible tiles without modifying the underlying map.
+struct TileCollector<'a, T> {
+    map: &'a T,
+    tiles: Vec<IVec2>,
+}
+
+impl<'a, T: VisibilityMap> VisibilityMap for TileCollector<'a, T> {
+    fn is_opaque(&self, p: impl GridPoint) -> bool {
+        self.map.is_opaque(p)
+    }
+
+    fn is_in_bounds(&self, p: impl GridPoint) -> bool {
+        self.map.is_in_bounds(p)
+    }
+
+    fn set_visible(&mut self, p: impl GridPoint) {
+        if self.map.is_in_bounds(p) {
+            self.tiles.push(p.as_ivec2());
+        }
+    }
+
+    fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+        self.map.dist(a, b)
+    }
+
+    fn in_range(&self, a: impl GridPoint, b: impl GridPoint, range: i32) -> bool {
+        self.map.in_range(a, b, range)
+    }
+}
+
+/// Writes visible tiles into a separate grid without modifying the underlying map.
+struct GridWriter<'a, T> {
+    map: &'a T,
+    visible: &'a mut Grid<bool>,
+}
+
+impl<'a, T: VisibilityMap> VisibilityMap for GridWriter<'a, T> {
+    fn is_opaque(&self, p: impl GridPoint) -> bool {
+        self.map.is_opaque(p)
+    }
+
+    fn is_in_bounds(&self, p: impl GridPoint) -> bool {
+        self.map.is_in_bounds(p)
+    }
+
+    fn set_visible(&mut self, p: impl GridPoint) {
+        if self.visible.in_bounds(p) {
+            self.visible[p] = true;
+        }
+    }
+
+    fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+        self.map.dist(a, b)
+    }
+
+    fn in_range(&self, a: impl GridPoint, b: impl GridPoint, range: i32) -> bool {
+        self.map.in_range(a, b, range)
+    }
+}
+
+/// Treats tiles as opaque if either the underlying map or `opaque` says so,
+/// without modifying the underlying map.
+struct OpaqueIf<'a, T, F> {
+    map: &'a T,
+    opaque: F,
+}
+
+impl<'a, T: VisibilityMap, F: Fn(IVec2) -> bool> VisibilityMap for OpaqueIf<'a, T, F> {
+    fn is_opaque(&self, p: impl GridPoint) -> bool {
+        self.map.is_opaque(p) || (self.opaque)(p.as_ivec2())
+    }
+
+    fn is_in_bounds(&self, p: impl GridPoint) -> bool {
+        self.map.is_in_bounds(p)
+    }
+
+    fn set_visible(&mut self, _: impl GridPoint) {}
+
+    fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+        self.map.dist(a, b)
+    }
+
+    fn in_range(&self, a: impl GridPoint, b: impl GridPoint, range: i32) -> bool {
+        self.map.in_range(a, b, range)
+    }
+}
+
+/// Limits a map's bounds to the positions accepted by `in_bounds`.
+struct BoundedBy<'a, T, F> {
+    map: &'a mut T,
+    in_bounds: F,
+}
+
+impl<'a, T: VisibilityMap, F: Fn(IVec2) -> bool> VisibilityMap for BoundedBy<'a, T, F> {
+    fn is_opaque(&self, p: impl GridPoint) -> bool {
+        self.map.is_opaque(p)
+    }
+
+    fn is_in_bounds(&self, p: impl GridPoint) -> bool {
+        self.map.is_in_bounds(p) && (self.in_bounds)(p.as_ivec2())
+    }
+
+    fn set_visible(&mut self, p: impl GridPoint) {
+        self.map.set_visible(p);
+    }
+
+    fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+        self.map.dist(a, b)
+    }
+
+    fn in_range(&self, a: impl GridPoint, b: impl GridPoint, range: i32) -> bool {
+        self.map.in_range(a, b, range)
+    }
+}
+
+/// Converts offset square positions to the axial positions of a hex map.
+struct HexMap<'a, T>(&'a mut T);
+
+impl<'a, T: VisibilityMap> VisibilityMap for HexMap<'a, T> {
+    fn is_opaque(&self, p: impl GridPoint) -> bool {
+        self.0.is_opaque(offset_to_axial(p.as_ivec2()))
+    }
+
+    fn is_in_bounds(&self, p: impl GridPoint) -> bool {
+        self.0.is_in_bounds(offset_to_axial(p.as_ivec2()))
+    }
+
+    fn set_visible(&mut self, p: impl GridPoint) {
+        self.0.set_visible(offset_to_axial(p.as_ivec2()));
+    }
+
+    fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+        hex_dist(offset_to_axial(a.as_ivec2()), offset_to_axial(b.as_ivec2())) as f32
+    }
+}
+
+/// Lets light pass through positions outside of the map, without ever marking
+/// them visible.
+struct TransparentEdges<'a, T>(&'a mut T);
+
+impl<'a, T: VisibilityMap> VisibilityMap for TransparentEdges<'a, T> {
+    fn is_opaque(&self, p: impl GridPoint) -> bool {
+        self.0.is_in_bounds(p) && self.0.is_opaque(p)
+    }
+
+    fn is_in_bounds(&self, _: impl GridPoint) -> bool {
+        true
+    }
+
+    fn set_visible(&mut self, p: impl GridPoint) {
+        if self.0.is_in_bounds(p) {
+            self.0.set_visible(p);
+        }
+    }
+
+    fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+        self.0.dist(a, b)
+    }
+
+    fn in_range(&self, a: impl GridPoint, b: impl GridPoint, range: i32) -> bool {
+        self.0.in_range(a, b, range)
+    }
+}
+
+/// Treats every position as being in bounds.
+struct Unbounded<'a, T>(&'a mut T);
+
+impl<'a, T: VisibilityMap> VisibilityMap for Unbounded<'a, T> {
+    fn is_opaque(&self, p: impl GridPoint) -> bool {
+        self.0.is_opaque(p)
+    }
+
+    fn is_in_bounds(&self, _: impl GridPoint) -> bool {
+        true
+    }
+
+    fn set_visible(&mut self, p: impl GridPoint) {
+        self.0.set_visible(p)
+    }
+
+    fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+        self.0.dist(a, b)
+    }
+
+    fn in_range(&self, a: impl GridPoint, b: impl GridPoint, range: i32) -> bool {
+        self.0.in_range(a, b, range)
+    }
+}
+
+/// Wraps positions around the edges of a map.
+struct WrapMap<'a, T> {
+    map: &'a mut T,
+    size: IVec2,
+}
+
+impl<'a, T> WrapMap<'a, T> {
+    fn wrap(&self, p: impl GridPoint) -> IVec2 {
+        IVec2::new(p.x().rem_euclid(self.size.x), p.y().rem_euclid(self.size.y))
+    }
+}
+
+impl<'a, T: VisibilityMap> VisibilityMap for WrapMap<'a, T> {
+    fn is_opaque(&self, p: impl GridPoint) -> bool {
+        self.map.is_opaque(self.wrap(p))
+    }
+
+    fn is_in_bounds(&self, _: impl GridPoint) -> bool {
+        true
+    }
+
+    fn set_visible(&mut self, p: impl GridPoint) {
+        let p = self.wrap(p);
+        self.map.set_visible(p)
+    }
+
+    fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+        self.map.dist(a, b)
+    }
+
+    fn in_range(&self, a: impl GridPoint, b: impl GridPoint, range: i32) -> bool {
+        self.map.in_range(a, b, range)
+    }
+}
+
+/// Ignores any visible tiles closer than `min_range` to the origin.
+struct RingMap<'a, T> {
+    map: &'a mut T,
+    origin: IVec2,
+    min_range: i32,
+}
+
+impl<'a, T: VisibilityMap> VisibilityMap for RingMap<'a, T> {
+    fn is_opaque(&self, p: impl GridPoint) -> bool {
+        self.map.is_opaque(p)
+    }
+
+    fn is_in_bounds(&self, p: impl GridPoint) -> bool {
+        self.map.is_in_bounds(p)
+    }
+
+    fn set_visible(&mut self, p: impl GridPoint) {
+        let d = self.map.dist(IVec2::ZERO, p.as_ivec2() - self.origin);
+        if d >= self.min_range as f32 {
+            self.map.set_visible(p)
+        }
+    }
+
+    fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+        self.map.dist(a, b)
+    }
+
+    fn in_range(&self, a: impl GridPoint, b: impl GridPoint, range: i32) -> bool {
+        self.map.in_range(a, b, range)
+    }
+}
+
+/// Iterates over the points of a straight line between two points, excluding
+/// the start.
+struct Line {
+    p: IVec2,
+    end: IVec2,
+    delta: IVec2,
+    step: IVec2,
+    err: i32,
+}
+
+impl Line {
+    fn new(start: IVec2, end: IVec2) -> Self {
+        let d = end - start;
+        let delta = IVec2::new(d.x.abs(), -d.y.abs());
+        Self {
+            p: start,
+            end,
+            delta,
+            step: d.signum(),
+            err: delta.x + delta.y,
+        }
+    }
+}
+
+impl Iterator for Line {
+    type Item = IVec2;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.p == self.end {
+            return None;
+        }
+        let e2 = self.err * 2;
+        if e2 >= self.delta.y {
+            self.err += self.delta.y;
+            self.p.x += self.step.x;
+        }
+        if e2 <= self.delta.x {
+            self.err += self.delta.x;
+            self.p.y += self.step.y;
+        }
+        Some(self.p)
+    }
+}
+
+/// How far the fov extends from its origin.
+#[derive(Clone, Copy)]
+enum Range {
+    /// Clip the fov using the map's [VisibilityMap::dist].
+    Radius(i32),
+    /// Clip the fov using the map's [VisibilityMap::dist] with a fractional radius.
+    Fractional(f32),
+    /// Clip the fov to an ellipse with the given radii along the world axes.
+    Ellipse(IVec2),
+    /// Clip the fov to a rectangle with the given corner offsets from the origin.
+    Box(IVec2, IVec2),
+    /// Clip each octant using the map's [VisibilityMap::in_range] with its own
+    /// radius.
+    PerOctant([i32; 8]),
+    /// Compute every tile up to the given column without clipping.
+    Columns(i32),
+}
+
+impl Range {
+    /// The furthest column from the origin in any octant.
+    fn max(&self) -> i32 {
+        match self {
+            Range::Radius(range) => *range,
+            Range::Fractional(range) => range.ceil() as i32,
+            Range::Ellipse(radii) => radii.max_element(),
+            Range::Box(min, max) => (-*min).max(*max).max_element(),
+            Range::PerOctant(ranges) => ranges.iter().copied().max().unwrap_or(0),
+            Range::Columns(x) => *x,
+        }
+    }
+
+    /// Returns the distance to the given octant-local point if it's within range.
+    fn clip<T: VisibilityMap>(&self, x: i32, y: i32, octant: i32, map: &T) -> Option<f32> {
+        match *self {
+            Range::Radius(range) => {
+                let p = IVec2::new(x, y);
+                (range < 0 || map.in_range(IVec2::ZERO, p, range)).then(|| map.dist(IVec2::ZERO, p))
+            }
+            Range::Fractional(range) => {
+                let dist = map.dist(IVec2::ZERO, IVec2::new(x, y));
+                (dist <= range).then_some(dist)
+            }
+            Range::Ellipse(radii) => {
+                // Octants swap axes, so the radii are applied to the world offset
+                let p = transform(x, y, octant, IVec2::ZERO).abs();
+                let (px, py) = (p.x as i64, p.y as i64);
+                let (rx, ry) = (radii.x as i64, radii.y as i64);
+                (px * px * ry * ry + py * py * rx * rx <= rx * rx * ry * ry)
+                    .then(|| p.as_vec2().length())
+            }
+            Range::Box(min, max) => {
+                let p = transform(x, y, octant, IVec2::ZERO);
+                (p.x >= min.x && p.y >= min.y && p.x <= max.x && p.y <= max.y)
+                    .then(|| p.as_vec2().length())
+            }
+            Range::PerOctant(ranges) => {
+                let p = IVec2::new(x, y);
+                map.in_range(IVec2::ZERO, p, ranges[octant as usize])
+                    .then(|| map.dist(IVec2::ZERO, p))
+            }
+            Range::Columns(_) => Some(map.dist(IVec2::ZERO, IVec2::new(x, y))),
+        }
+    }
+}
+
+/// A visible tile found by the fov algorithm.
+#[derive(Clone, Copy)]
+struct Tile {
+    /// The world position of the tile.
+    pos: IVec2,
+    /// The distance from the origin, as used to clip the fov to its range.
+    dist: f32,
+    /// The octant the tile was found in, or -1 for the origin.
+    octant: i32,
+    /// The octant-local position of the tile.
+    local: IVec2,
+    /// Whether the tile is opaque.
+    opaque: bool,
+}
+
+/// Settings shared by every section of a single fov computation.
+#[derive(Clone, Copy)]
+struct Params {
+    origin: IVec2,
+    range: Range,
+    symmetry: Symmetry,
+    diagonal: DiagonalRule,
+    /// Whether the origin itself is marked visible.
+    include_origin: bool,
+    /// A bit mask of the octants to compute.
+    octants: u8,
+    opaque_origin: OpaqueOrigin,
+}
+
+impl Params {
+    fn new(origin: impl GridPoint, range: Range) -> Self {
+        Self {
+            origin: origin.as_ivec2(),
+            range,
+            symmetry: Symmetry::default(),
+            diagonal: DiagonalRule::default(),
+            include_origin: true,
+            octants: u8::MAX,
+            opaque_origin: OpaqueOrigin::default(),
+        }
+    }
+
+    /// Whether the origin is opaque and sees no further than the tiles next to
+    /// it.
+    fn origin_blocked<T: VisibilityMap>(&self, map: &T) -> bool {
+        self.opaque_origin != OpaqueOrigin::Transparent
+            && map.is_in_bounds(self.origin)
+            && map.is_opaque(self.origin)
+    }
+}
+
+fn compute_range<T: VisibilityMap>(params: Params, map: &mut T) {
+    let _ = compute_range_with(params, map, &mut mark_map);
+}
+
+/// Marks a visible tile on the map it was found in.
+fn mark_map<T: VisibilityMap>(map: &mut T, tile: Tile) -> ControlFlow<()> {
+    map.set_visible(tile.pos);
+    ControlFlow::Continue(())
+}
+
+/// Compute the fov, passing each visible tile to `mark` instead of
+/// [VisibilityMap::set_visible].
+///
+/// Stops as soon as `mark` returns [ControlFlow::Break].
+fn compute_range_with<T: VisibilityMap, F: FnMut(&mut T, Tile) -> ControlFlow<()>>(
+    params: Params,
+    map: &mut T,
+    mark: &mut F,
+) -> ControlFlow<()> {
+    compute_range_in(params, map, &mut Vec::new(), mark)
+}
+
+/// Compute the fov using `sections` as the work stack.
+fn compute_range_in<T: VisibilityMap, F: FnMut(&mut T, Tile) -> ControlFlow<()>>(
+    params: Params,
+    map: &mut T,
+    sections: &mut Vec<Section>,
+    mark: &mut F,
+) -> ControlFlow<()> {
+    let origin = params.origin;
+    sections.clear();
+    if params.include_origin && map.is_in_bounds(origin) {
+        mark(
+            map,
+            Tile {
+                pos: origin,
+                dist: 0.0,
+                octant: -1,
+                local: IVec2::ZERO,
+                opaque: map.is_opaque(origin),
+            },
+        )?;
+    }
+    if params.range.max() < 1 {
+        return ControlFlow::Continue(());
+    }
+    if params.origin_blocked(map) {
+        return match params.opaque_origin {
+            OpaqueOrigin::Adjacent => mark_adjacent(&params, map, mark),
+            _ => ControlFlow::Continue(()),
+        };
+    }
+
+    sections.extend(Section::octants().filter(|s| params.octants & (1 << s.octant) != 0));
+    while let Some(mut section) = sections.pop() {
+        while compute_column(&mut section, &params, map, sections, mark)? {}
+    }
+    ControlFlow::Continue(())
+}
+
+/// Mark the tiles next to the origin that are in range, without computing any
+/// further.
+fn mark_adjacent<T: VisibilityMap, F: FnMut(&mut T, Tile) -> ControlFlow<()>>(
+    params: &Params,
+    map: &mut T,
+    mark: &mut F,
+) -> ControlFlow<()> {
+    for octant in (0..8).filter(|o| params.octants & (1 << o) != 0) {
+        for y in 0..=1 {
+            if let Some(dist) = params.range.clip(1, y, octant, map) {
+                let opaque = blocks_light(1, y, octant, params.origin, map);
+                set_visible(1, y, octant, params.origin, dist, opaque, map, mark)?;
+            }
+        }
+    }
+    ControlFlow::Continue(())
+}
+
+/// Compute a single section of an octant, starting from the first column, and
+/// mark visible tiles on the map. The origin is not marked.
+pub(crate) fn compute_section<T: VisibilityMap>(
+    origin: IVec2,
+    range: i32,
+    octant: i32,
+    top: Slope,
+    bottom: Slope,
+    map: &mut T,
+) {
+    let params = Params::new(origin, Range::Radius(range));
+    let mut sections = vec![Section {
+        octant,
+        x: 1,
+        top,
+        bottom,
+    }];
+    while let Some(mut section) = sections.pop() {
+        while let ControlFlow::Continue(true) =
+            compute_column(&mut section, &params, map, &mut sections, &mut mark_map)
+        {}
+    }
+}
+
+/// A part of an octant bounded by two slopes that still needs to be computed.
+#[derive(Clone)]
+struct Section {
+    octant: i32,
+    x: i32,
+    top: Slope,
+    bottom: Slope,
+}
+
+impl Section {
+    /// Every octant in full, from last to first so octant 0 ends up on top of
+    /// the stack.
+    fn octants() -> impl Iterator<Item = Section> {
+        (0..8).rev().map(|octant| Section {
+            octant,
+            x: 1,
+            top: Slope { x: 1, y: 1 },
+            bottom: Slope { x: 1, y: 0 },
+        })
+    }
+}
+
+/// Compute the visibility of the section's current column and advance it to the
+/// next one. Any sections split off by opaque tiles are pushed to `sections`.
+///
+/// Returns false once the section is finished, or breaks if `mark` does.
+fn compute_column<T: VisibilityMap, F: FnMut(&mut T, Tile) -> ControlFlow<()>>(
+    section: &mut Section,
+    params: &Params,
+    map: &mut T,
+    sections: &mut Vec<Section>,
+    mark: &mut F,
+) -> ControlFlow<(), bool> {
+    let x = section.x;
+    if x > params.range.max() {
+        return ControlFlow::Continue(false);
+    }
+    section.x += 1;
+
+    let octant = section.octant;
+    let top = &mut section.top;
+    let bottom = &mut section.bottom;
+
+    let y_coords = compute_y_coordinate(octant, params.origin, x, map, top, bottom);
+
+    let top_y = y_coords.x;
+    let bottom_y = y_coords.y;
+
+    compute_visiblity(
+        top_y, bottom_y, params, octant, x, map, top, bottom, sections, mark,
+    )
+}
+
+fn compute_y_coordinate<T: VisibilityMap>(
+    octant: i32,
+    origin: IVec2,
+    x: i32,
+    map: &mut T,
+    top: &mut Slope,
+    bottom: &mut Slope,
+) -> IVec2 {
+    let mut top_y;
+    if top.x == 1 {
+        top_y = x;
+    } else {
+        top_y = top.column_y(x);
+
+        if blocks_light(x, top_y, octant, origin, map) {
+            if top.greater_or_equal(top_y * 2 + 1, x * 2)
+                && !blocks_light(x, top_y + 1, octant, origin, map)
+            {
+                top_y += 1;
+            }
+        } else {
+            let mut ax = x * 2;
+            if blocks_light(x + 1, top_y + 1, octant, origin, map) {
+                ax += 1;
+            }
+            if top.greater(top_y * 2 + 1, ax) {
+                top_y += 1;
+            }
+        }
+    }
+
+    let mut bottom_y;
+    if bottom.y == 0 {
+        bottom_y = 0;
+    } else {
+        bottom_y = bottom.column_y(x);
+
+        if bottom.greater_or_equal(bottom_y * 2 + 1, x * 2)
+            && blocks_light(x, bottom_y, octant, origin, map)
+            && !blocks_light(x, bottom_y + 1, octant, origin, map)
+        {
+            bottom_y += 1;
+        }
+    }
+    IVec2::new(top_y, bottom_y)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn compute_visiblity<T: VisibilityMap, F: FnMut(&mut T, Tile) -> ControlFlow<()>>(
+    top_y: i32,
+    bottom_y: i32,
+    params: &Params,
+    octant: i32,
+    x: i32,
+    map: &mut T,
+    top: &mut Slope,
+    bottom: &mut Slope,
+    sections: &mut Vec<Section>,
+    mark: &mut F,
+) -> ControlFlow<(), bool> {
+    let origin = params.origin;
+    let range = params.range;
+    let mut was_opaque = -1;
+
+    for y in (bottom_y..=top_y).rev() {
+        if let Some(dist) = range.clip(x, y, octant, map) {
+            let is_opaque = blocks_light(x, y, octant, origin, map);
+
+            // Less symmetrical
+            // let is_visible = is_opaque ||
+            // (
+            //     (y != top_y || top.greater(y * 4 - 1, x * 4 + 1)) &&
+            //     (y != bottom_y || bottom.less(y * 4 + 1, x * 4 - 1))
+            // );
+
+            // Better symmetry
+            let is_visible = (is_opaque && params.symmetry == Symmetry::Balanced) || // Full symmetry skips the is_opaque check but has more artifacts in hallways
+            (
+                (y != top_y || top.greater_or_equal(y, x)) &&
+                (y != bottom_y || bottom.less_or_equal(y, x))
+            );
+
+            if is_visible {
+                set_visible(x, y, octant, origin, dist, is_opaque, map, mark)?;
+            }
+
+            if x != range.max() {
+                if is_opaque {
+                    if was_opaque == 0 {
+                        let mut nx = x * 2;
+                        let ny = y * 2 + 1;
+                        if blocks_light(x, y + 1, octant, origin, map)
+                            || (params.diagonal == DiagonalRule::Blocking
+                                && blocks_light(x - 1, y + 1, octant, origin, map))
+                        {
+                            nx -= 1;
+                        }
+                        if top.greater(ny, nx) {
+                            if y == bottom_y {
+                                *bottom = Slope { y: ny, x: nx };
+                                break;
+                            } else {
+                                sections.push(Section {
+                                    octant,
+                                    x: x + 1,
+                                    top: *top,
+                                    bottom: Slope { y: ny, x: nx },
+                                });
+                            }
+                        } else if y == bottom_y {
+                            return ControlFlow::Continue(false);
+                        }
+                    }
+                    was_opaque = 1;
+                } else {
+                    if was_opaque > 0 {
+                        let mut nx = x * 2;
+                        let ny = y * 2 + 1;
+                        if blocks_light(x + 1, y + 1, octant, origin, map)
+                            || (params.diagonal == DiagonalRule::Blocking
+                                && blocks_light(x + 1, y, octant, origin, map))
+                        {
+                            nx += 1;
+                        }
+                        if bottom.greater_or_equal(ny, nx) {
+                            return ControlFlow::Continue(false);
+                        }
+                        *top = Slope { y: ny, x: nx };
+                    }
+                    was_opaque = 0;
+                }
+            }
+        }
+    }
+
+    ControlFlow::Continue(was_opaque == 0)
+}
+
+fn blocks_light<T: VisibilityMap>(x: i32, y: i32, octant: i32, origin: IVec2, map: &mut T) -> bool {
+    let p = transform(x, y, octant, origin);
+    if !map.is_in_bounds(p) {
+        return true;
+    }
+    map.is_opaque(p)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn set_visible<T: VisibilityMap, F: FnMut(&mut T, Tile) -> ControlFlow<()>>(
+    x: i32,
+    y: i32,
+    octant: i32,
+    origin: IVec2,
+    dist: f32,
+    opaque: bool,
+    map: &mut T,
+    mark: &mut F,
+) -> ControlFlow<()> {
+    let p = transform(x, y, octant, origin);
+    if map.is_in_bounds(p) {
+        mark(
+            map,
+            Tile {
+                pos: p,
+                dist,
+                octant,
+                local: IVec2::new(x, y),
+                opaque,
+            },
+        )?;
+    }
+    ControlFlow::Continue(())
+}
+
+/// Caps `range` to the manhattan distance from the origin to the furthest tile
+/// of a `size` map starting at `[0,0]`, which no other metric exceeds.
+fn clamp_range(origin: IVec2, range: i32, size: IVec2) -> i32 {
+    let far = |o: i32, len: i32| (o as i64).abs().max((o as i64 - (len as i64 - 1)).abs());
+    let max = far(origin.x, size.x) + far(origin.y, size.y);
+    match max < range as i64 {
+        true => max as i32,
+        false => range,
+    }
+}
+
+/// Transform an octant-local point to a world position.
+fn transform(x: i32, y: i32, octant: i32, origin: IVec2) -> IVec2 {
+    let (mut nx, mut ny) = origin.into();
+    match octant {
+        0 => {
+            nx += x;
+            ny -= y;
+        }
+        1 => {
+            nx += y;
+            ny -= x;
+        }
+        2 => {
+            nx -= y;
+            ny -= x;
+        }
+        3 => {
+            nx -= x;
+            ny -= y;
+        }
+        4 => {
+            nx -= x;
+            ny += y;
+        }
+        5 => {
+            nx -= y;
+            ny += x;
+        }
+        6 => {
+            nx += y;
+            ny += x;
+        }
+        7 => {
+            nx += x;
+            ny += y;
+        }
+        _ => {}
+    }
+    IVec2::new(nx, ny)
+}
@@ -0,0 +1,61 @@
+use sark_grids::{Grid, Size2d};
+
+use crate::{fov, GridPoint, VisibilityMap};
+
+/// Tracks the currently visible tiles along with every tile that has ever been
+/// visible.
+///
+/// Useful for rendering previously explored tiles that are no longer in view.
+#[derive(Debug, Clone)]
+pub struct FovMemory {
+    visible: Grid<bool>,
+    explored: Grid<bool>,
+}
+
+impl FovMemory {
+    /// Create a memory of the given size with no visible or explored tiles.
+    pub fn new(size: impl Size2d) -> Self {
+        Self {
+            visible: Grid::default(size),
+            explored: Grid::default(size),
+        }
+    }
+
+    /// Recompute the currently visible tiles and add them to the explored tiles.
+    ///
+    /// The map itself is not modified.
+    pub fn update<T: VisibilityMap>(&mut self, origin: impl GridPoint, range: i32, map: &T) {
+        fov::compute_into_grid(origin, range, map, &mut self.visible);
+        for (explored, visible) in self.explored.iter_mut().zip(self.visible.iter()) {
+            *explored |= *visible;
+        }
+    }
+
+    /// Whether the tile at `p` was visible during the last update. Out of bounds
+    /// tiles are never visible.
+    pub fn is_visible(&self, p: impl GridPoint) -> bool {
+        self.visible.in_bounds(p) && self.visible[p]
+    }
+
+    /// Whether the tile at `p` has ever been visible. Out of bounds tiles are
+    /// never explored.
+    pub fn is_explored(&self, p: impl GridPoint) -> bool {
+        self.explored.in_bounds(p) && self.explored[p]
+    }
+
+    /// The tiles that were visible during the last update.
+    pub fn visible(&self) -> &Grid<bool> {
+        &self.visible
+    }
+
+    /// Every tile that has ever been visible.
+    pub fn explored(&self) -> &Grid<bool> {
+        &self.explored
+    }
+
+    /// Forget all visible and explored tiles.
+    pub fn clear(&mut self) {
+        self.visible.iter_mut().for_each(|v| *v = false);
+        self.explored.iter_mut().for_each(|v| *v = false);
+    }
+}
@@ -0,0 +1,80 @@
+use glam::IVec2;
+use sark_grids::Size2d;
+
+use crate::{fov, GridPoint, VisibilityMap};
+
+/// The visible tiles of a single fov in a rectangular region starting at
+/// `[0,0]`, stored as one bit per tile.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FovResult {
+    width: usize,
+    height: usize,
+    bits: Vec<u64>,
+}
+
+impl FovResult {
+    /// Compute the fov in a map from the given position, keeping the visible
+    /// tiles inside the region of the given size.
+    ///
+    /// The map itself is not modified.
+    pub fn new<T: VisibilityMap>(
+        origin: impl GridPoint,
+        range: i32,
+        size: impl Size2d,
+        map: &T,
+    ) -> Self {
+        let (width, height) = (size.width(), size.height());
+        let mut result = Self {
+            width,
+            height,
+            bits: vec![0; (width * height).div_ceil(64)],
+        };
+        fov::compute_unique(origin, range, map, |p| {
+            if let Some(i) = result.index(p) {
+                result.bits[i / 64] |= 1 << (i % 64);
+            }
+        });
+        result
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Whether the tile at `p` is visible. Tiles outside the region are never
+    /// visible.
+    pub fn is_visible(&self, p: impl GridPoint) -> bool {
+        match self.index(p) {
+            Some(i) => self.bits[i / 64] & (1 << (i % 64)) != 0,
+            None => false,
+        }
+    }
+
+    /// The visible tiles, in `y * width + x` order.
+    pub fn visible_tiles(&self) -> impl Iterator<Item = IVec2> + '_ {
+        (0..self.width * self.height)
+            .filter(|i| self.bits[i / 64] & (1 << (i % 64)) != 0)
+            .map(|i| IVec2::new((i % self.width) as i32, (i / self.width) as i32))
+    }
+
+    /// The number of visible tiles.
+    pub fn len(&self) -> usize {
+        self.bits.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Whether no tiles are visible.
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|w| *w == 0)
+    }
+
+    fn index(&self, p: impl GridPoint) -> Option<usize> {
+        let p = p.as_ivec2();
+        (p.x >= 0 && p.y >= 0 && (p.x as usize) < self.width && (p.y as usize) < self.height)
+            .then(|| p.y as usize * self.width + p.x as usize)
+    }
+}
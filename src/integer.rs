@@ -0,0 +1,241 @@
+//! An fov that only uses integer math and never allocates, for `no_std` targets.
+//!
+//! This computes the same tiles as `fov::compute` with the default settings, but positions are plain `[i32; 2]` points, the
+//! range is clipped with an exact squared distance instead of a float one, and
+//! octants are computed recursively instead of with a work stack. It's the only
+//! fov available when the default `std` feature is disabled.
+
+/// A position on the map.
+pub type Point = [i32; 2];
+
+/// Compute the fov in a `width` by `height` map starting at `[0,0]`, reading
+/// opaque tiles from `is_opaque` and passing each visible tile to
+/// `mark_visible`.
+///
+/// Tiles outside the map block vision and are never passed to either closure.
+/// Like [OpaqueOrigin::OnlySelf](crate::OpaqueOrigin::OnlySelf), an opaque
+/// origin only sees itself. Note `mark_visible` may be called more than once
+/// for the same tile.
+///
+/// Each section split off by an opaque tile recurses, so the stack depth grows
+/// with the range, up to the size of the map.
+pub fn compute(
+    origin: Point,
+    range: i32,
+    width: i32,
+    height: i32,
+    is_opaque: impl Fn(Point) -> bool,
+    mut mark_visible: impl FnMut(Point),
+) {
+    let map = Map {
+        origin,
+        width,
+        height,
+        is_opaque,
+    };
+    if map.is_in_bounds(origin) {
+        mark_visible(origin);
+        if (map.is_opaque)(origin) {
+            return;
+        }
+    }
+    let range = map.clamp_range(range);
+    for octant in 0..8 {
+        compute_octant(
+            &map,
+            range,
+            octant,
+            1,
+            Slope { y: 1, x: 1 },
+            Slope { y: 0, x: 1 },
+            &mut mark_visible,
+        );
+    }
+}
+
+struct Map<F> {
+    origin: Point,
+    width: i32,
+    height: i32,
+    is_opaque: F,
+}
+
+impl<F: Fn(Point) -> bool> Map<F> {
+    fn is_in_bounds(&self, p: Point) -> bool {
+        p[0] >= 0 && p[1] >= 0 && p[0] < self.width && p[1] < self.height
+    }
+
+    fn blocks_light(&self, x: i32, y: i32, octant: i32) -> bool {
+        let p = transform(x, y, octant, self.origin);
+        !self.is_in_bounds(p) || (self.is_opaque)(p)
+    }
+
+    /// Caps `range` to the manhattan distance from the origin to the furthest
+    /// tile of the map.
+    fn clamp_range(&self, range: i32) -> i32 {
+        let far = |o: i32, len: i32| (o as i64).abs().max((o as i64 - (len as i64 - 1)).abs());
+        let max = far(self.origin[0], self.width) + far(self.origin[1], self.height);
+        match max < range as i64 {
+            true => max as i32,
+            false => range,
+        }
+    }
+}
+
+/// A slope of `y / x` in octant-local coordinates, compared in i64.
+#[derive(Clone, Copy)]
+struct Slope {
+    y: i32,
+    x: i32,
+}
+
+impl Slope {
+    fn greater(&self, y: i32, x: i32) -> bool {
+        self.y as i64 * x as i64 > self.x as i64 * y as i64
+    }
+
+    fn greater_or_equal(&self, y: i32, x: i32) -> bool {
+        self.y as i64 * x as i64 >= self.x as i64 * y as i64
+    }
+
+    fn less_or_equal(&self, y: i32, x: i32) -> bool {
+        self.y as i64 * x as i64 <= self.x as i64 * y as i64
+    }
+
+    fn column_y(&self, x: i32) -> i32 {
+        let (sx, sy) = (self.x as i64, self.y as i64);
+        (((x as i64 * 2 - 1) * sy + sx) / (sx * 2)) as i32
+    }
+}
+
+fn in_range(x: i32, y: i32, range: i32) -> bool {
+    let (x, y, r) = (x as i64, y as i64, range as i64);
+    x * x + y * y <= r * r
+}
+
+/// Compute the part of an octant between `top` and `bottom`, starting from
+/// column `x`.
+fn compute_octant<F: Fn(Point) -> bool, M: FnMut(Point)>(
+    map: &Map<F>,
+    range: i32,
+    octant: i32,
+    mut x: i32,
+    mut top: Slope,
+    mut bottom: Slope,
+    mark: &mut M,
+) {
+    while x <= range {
+        let mut top_y;
+        if top.x == 1 {
+            top_y = x;
+        } else {
+            top_y = top.column_y(x);
+
+            if map.blocks_light(x, top_y, octant) {
+                if top.greater_or_equal(top_y * 2 + 1, x * 2)
+                    && !map.blocks_light(x, top_y + 1, octant)
+                {
+                    top_y += 1;
+                }
+            } else {
+                let mut ax = x * 2;
+                if map.blocks_light(x + 1, top_y + 1, octant) {
+                    ax += 1;
+                }
+                if top.greater(top_y * 2 + 1, ax) {
+                    top_y += 1;
+                }
+            }
+        }
+
+        let mut bottom_y;
+        if bottom.y == 0 {
+            bottom_y = 0;
+        } else {
+            bottom_y = bottom.column_y(x);
+
+            if bottom.greater_or_equal(bottom_y * 2 + 1, x * 2)
+                && map.blocks_light(x, bottom_y, octant)
+                && !map.blocks_light(x, bottom_y + 1, octant)
+            {
+                bottom_y += 1;
+            }
+        }
+
+        let mut was_opaque = -1;
+        for y in (bottom_y..=top_y).rev() {
+            if !in_range(x, y, range) {
+                continue;
+            }
+            let is_opaque = map.blocks_light(x, y, octant);
+            let is_visible = is_opaque
+                || ((y != top_y || top.greater_or_equal(y, x))
+                    && (y != bottom_y || bottom.less_or_equal(y, x)));
+
+            if is_visible {
+                let p = transform(x, y, octant, map.origin);
+                if map.is_in_bounds(p) {
+                    mark(p);
+                }
+            }
+
+            if x == range {
+                continue;
+            }
+            if is_opaque {
+                if was_opaque == 0 {
+                    let mut nx = x * 2;
+                    let ny = y * 2 + 1;
+                    if map.blocks_light(x, y + 1, octant) {
+                        nx -= 1;
+                    }
+                    if top.greater(ny, nx) {
+                        if y == bottom_y {
+                            bottom = Slope { y: ny, x: nx };
+                            break;
+                        }
+                        let split = Slope { y: ny, x: nx };
+                        compute_octant(map, range, octant, x + 1, top, split, mark);
+                    } else if y == bottom_y {
+                        return;
+                    }
+                }
+                was_opaque = 1;
+            } else {
+                if was_opaque > 0 {
+                    let mut nx = x * 2;
+                    let ny = y * 2 + 1;
+                    if map.blocks_light(x + 1, y + 1, octant) {
+                        nx += 1;
+                    }
+                    if bottom.greater_or_equal(ny, nx) {
+                        return;
+                    }
+                    top = Slope { y: ny, x: nx };
+                }
+                was_opaque = 0;
+            }
+        }
+
+        if was_opaque != 0 {
+            return;
+        }
+        x += 1;
+    }
+}
+
+/// Transform an octant-local point to a world position.
+fn transform(x: i32, y: i32, octant: i32, origin: Point) -> Point {
+    let [ox, oy] = origin;
+    match octant {
+        0 => [ox + x, oy - y],
+        1 => [ox + y, oy - x],
+        2 => [ox - y, oy - x],
+        3 => [ox - x, oy - y],
+        4 => [ox - x, oy + y],
+        5 => [ox - y, oy + x],
+        6 => [ox + y, oy + x],
+        7 => [ox + x, oy + y],
+        _ => origin,
+    }
+}
@@ -0,0 +1,79 @@
+//! Lower level pieces of the fov algorithm, for building custom vision effects.
+//!
+//! Each octant is computed in its own local coordinates, where `x` is the
+//! distance from the origin along the octant's primary axis and `y` is the
+//! distance along its secondary axis, with `0 <= y <= x`. Octants are numbered
+//! 0-7 going clockwise from east-south-east.
+
+use crate::{fov, GridPoint, VisibilityMap};
+
+/// A slope of `y / x` in octant-local coordinates, stored as a rational number.
+///
+/// Comparisons are done in i64 since the products of two octant-local
+/// coordinates overflow i32 for ranges past ~23000 tiles.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slope {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Slope {
+    pub fn new(y: i32, x: i32) -> Self {
+        Self { x, y }
+    }
+
+    /// Whether this slope is greater than `y / x`.
+    pub fn greater(&self, y: i32, x: i32) -> bool {
+        self.y as i64 * x as i64 > self.x as i64 * y as i64
+    }
+
+    /// Whether this slope is greater than or equal to `y / x`.
+    pub fn greater_or_equal(&self, y: i32, x: i32) -> bool {
+        self.y as i64 * x as i64 >= self.x as i64 * y as i64
+    }
+
+    /// Whether this slope is less than `y / x`.
+    pub fn less(&self, y: i32, x: i32) -> bool {
+        (self.y as i64 * x as i64) < self.x as i64 * y as i64
+    }
+
+    /// Whether this slope is less than or equal to `y / x`.
+    pub fn less_or_equal(&self, y: i32, x: i32) -> bool {
+        self.y as i64 * x as i64 <= self.x as i64 * y as i64
+    }
+
+    /// The y coordinate where this slope crosses the middle of column x.
+    pub(crate) fn column_y(&self, x: i32) -> i32 {
+        let (sx, sy) = (self.x as i64, self.y as i64);
+        (((x as i64 * 2 - 1) * sy + sx) / (sx * 2)) as i32
+    }
+}
+
+/// Compute the visible tiles of a single octant between the `top` and `bottom`
+/// slopes and mark them on the map.
+///
+/// A full octant is bounded by `Slope::new(1, 1)` and `Slope::new(0, 1)`. The
+/// origin itself is not marked.
+///
+/// # Panics
+///
+/// Panics if `octant` is not in `0..8`, or if the slopes are not between 0 and 1
+/// with `top >= bottom`.
+pub fn compute_octant<T: VisibilityMap>(
+    origin: impl GridPoint,
+    range: i32,
+    octant: i32,
+    top: Slope,
+    bottom: Slope,
+    map: &mut T,
+) {
+    assert!((0..8).contains(&octant), "invalid octant {}", octant);
+    assert!(top.x > 0 && bottom.x > 0, "slopes must have a positive x");
+    assert!(
+        top.less_or_equal(1, 1)
+            && bottom.greater_or_equal(0, 1)
+            && top.greater_or_equal(bottom.y, bottom.x),
+        "slopes must be between 0 and 1 with top >= bottom"
+    );
+    fov::compute_section(origin.as_ivec2(), range, octant, top, bottom, map);
+}
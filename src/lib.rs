@@ -11,6 +11,7 @@
 //! # Example
 //! ```rust
 //! use adam_fov_rs::*;
+//! # #[cfg(feature = "std")] {
 //!
 //! // Create a 50x50 visibility map
 //! let mut map = VisibilityMap2d::default([50,50]);
@@ -23,32 +24,379 @@
 //!
 //! // The space directly above our opaque tile is not visible
 //! assert!(map[[15,16]].visible == false);
+//! # }
 //! ```
 //!
 //! *Taken from the terminal example*
 //! ![](images/fov.gif)
+//!
+//! # Coordinates
+//!
+//! Positions are passed as any [GridPoint], such as `[i32; 2]` or [IVec2].
+//! Unsigned or tuple positions can be converted first:
+//! ```rust
+//! use adam_fov_rs::*;
+//! # #[cfg(feature = "std")] {
+//!
+//! let mut map = VisibilityMap2d::default([50,50]);
+//!
+//! fov::compute(UVec2::new(15, 14).as_ivec2(), 5, &mut map);
+//! fov::compute(IVec2::from((15, 14)), 5, &mut map);
+//! fov::compute(UVec2::from((15_u32, 14_u32)).as_ivec2(), 5, &mut map);
+//! # }
+//! ```
+//!
+//! Unsigned values above `i32::MAX` wrap around to negative positions. A negative
+//! origin is out of bounds for a grid map, so only the tiles it can see inside
+//! the map will be marked.
+//!
+//! # `no_std`
+//!
+//! Everything above needs the default `std` feature. Without it the crate is
+//! `no_std` and only provides the allocation free [integer::compute], which
+//! takes plain `[i32; 2]` points and closures instead of a [VisibilityMap].
+
+#![cfg_attr(not(feature = "std"), no_std)]
+// The docs above link to items that need std
+#![cfg_attr(not(feature = "std"), allow(rustdoc::broken_intra_doc_links))]
 
-pub use glam::IVec2;
+#[cfg(feature = "std")]
 use glam::Vec2;
+#[cfg(feature = "std")]
+pub use glam::{IVec2, UVec2};
+#[cfg(feature = "std")]
 use sark_grids::Grid;
+#[cfg(feature = "std")]
 pub use sark_grids::GridPoint;
 
+#[cfg(feature = "std")]
+mod bool_grid_map;
+#[cfg(feature = "std")]
+pub use bool_grid_map::BoolGridMap;
+
+#[cfg(feature = "std")]
+mod fov_memory;
+#[cfg(feature = "std")]
+pub use fov_memory::FovMemory;
+
+#[cfg(feature = "std")]
+mod fov_result;
+#[cfg(feature = "std")]
+pub use fov_result::FovResult;
+
+#[cfg(feature = "std")]
+mod visibility_table;
+#[cfg(feature = "std")]
+pub use visibility_table::VisibilityTable;
+
+#[cfg(feature = "bevy")]
+mod plugin;
+#[cfg(feature = "bevy")]
+pub use plugin::{AdamFovPlugin, FovComputed, FovSource};
+
+#[cfg(feature = "std")]
 pub type VisibilityMap2d = Grid<VisibilityPoint>;
 
+/// The distance metric used to clip the fov to its range.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeMetric {
+    /// A circular fov.
+    #[default]
+    Euclidean,
+    /// A square fov. A tile is in range if it's within `range` tiles on both axes.
+    Chebyshev,
+    /// A diamond shaped fov. A tile is in range if the sum of its distance on
+    /// each axis is within `range`.
+    Manhattan,
+}
+
+#[cfg(feature = "std")]
+impl RangeMetric {
+    /// Returns the distance between two points using this metric.
+    pub fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+        let d = (b.as_ivec2() - a.as_ivec2()).abs();
+        match self {
+            RangeMetric::Euclidean => Vec2::distance(a.as_vec2(), b.as_vec2()),
+            RangeMetric::Chebyshev => d.max_element() as f32,
+            RangeMetric::Manhattan => (d.x + d.y) as f32,
+        }
+    }
+
+    /// Whether the distance between two points using this metric is within
+    /// `range`.
+    ///
+    /// Unlike comparing [RangeMetric::dist] to the range this is done in integers,
+    /// so it's exact even for very large distances.
+    pub fn in_range(&self, a: impl GridPoint, b: impl GridPoint, range: i32) -> bool {
+        self.in_range_bounded(a, b, range, RangeBound::Inclusive)
+    }
+
+    /// Whether the distance between two points using this metric is within
+    /// `range`, with `bound` deciding whether a distance of exactly `range`
+    /// counts.
+    ///
+    /// Like [RangeMetric::in_range] this is done exactly in integers.
+    pub fn in_range_bounded(
+        &self,
+        a: impl GridPoint,
+        b: impl GridPoint,
+        range: i32,
+        bound: RangeBound,
+    ) -> bool {
+        let (a, b) = (a.as_ivec2(), b.as_ivec2());
+        let x = (b.x as i64 - a.x as i64).abs();
+        let y = (b.y as i64 - a.y as i64).abs();
+        let r = range as i64;
+        let (d, r) = match self {
+            RangeMetric::Euclidean => (x * x + y * y, r * r),
+            RangeMetric::Chebyshev => (x.max(y), r),
+            RangeMetric::Manhattan => (x + y, r),
+        };
+        range >= 0
+            && match bound {
+                RangeBound::Inclusive => d <= r,
+                RangeBound::Exclusive => d < r,
+            }
+    }
+}
+
+/// Whether tiles at exactly the range's distance from the origin are in range.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeBound {
+    /// Tiles at exactly the range are in range.
+    #[default]
+    Inclusive,
+    /// Only tiles closer than the range are in range.
+    Exclusive,
+}
+
+/// Controls how strictly the fov is symmetric - if a tile `b` can be seen from
+/// `a`, then `a` can also be seen from `b`.
+///
+/// Visibility between non-opaque tiles is always symmetric. This only controls
+/// how opaque tiles are treated.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    /// Floor tiles are symmetric, but opaque tiles are always visible if any part
+    /// of them can be seen. This avoids artifacts along the walls of hallways.
+    #[default]
+    Balanced,
+    /// All tiles, including opaque ones, are symmetric. Useful when sight needs to
+    /// be fair in both directions, at the cost of some walls not being visible
+    /// in hallways and around corners.
+    ///
+    /// This is the more restrictive option for lighting walls - a wall is only
+    /// visible if the line to its center is unobstructed.
+    Full,
+}
+
+/// Controls whether vision can pass between two diagonally adjacent opaque tiles.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagonalRule {
+    /// Vision passes through the gap where two diagonal walls touch.
+    #[default]
+    Permissive,
+    /// Diagonal walls block vision as if they were a solid line.
+    Blocking,
+}
+
+/// What the fov sees from an opaque origin, such as a creature standing inside
+/// a wall.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpaqueOrigin {
+    /// The origin only sees itself.
+    #[default]
+    OnlySelf,
+    /// The origin sees the tiles right next to it that are in range, but
+    /// nothing further.
+    Adjacent,
+    /// The origin's opacity is ignored, so it sees the same tiles it would from
+    /// an open tile.
+    Transparent,
+}
+
+/// One of the eight 45 degree wedges the fov is computed in, named by its
+/// compass direction with +y pointing north.
+///
+/// Converts to and from the octant numbers (0-7) used by functions such as
+/// [fov::compute_octants].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Octant {
+    /// Octant 0, from +x towards -y.
+    EastSoutheast,
+    /// Octant 1, from -y towards +x.
+    SouthSoutheast,
+    /// Octant 2, from -y towards -x.
+    SouthSouthwest,
+    /// Octant 3, from -x towards -y.
+    WestSouthwest,
+    /// Octant 4, from -x towards +y.
+    WestNorthwest,
+    /// Octant 5, from +y towards -x.
+    NorthNorthwest,
+    /// Octant 6, from +y towards +x.
+    NorthNortheast,
+    /// Octant 7, from +x towards +y.
+    EastNortheast,
+}
+
+impl Octant {
+    /// All eight octants, in order.
+    pub const ALL: [Octant; 8] = [
+        Octant::EastSoutheast,
+        Octant::SouthSoutheast,
+        Octant::SouthSouthwest,
+        Octant::WestSouthwest,
+        Octant::WestNorthwest,
+        Octant::NorthNorthwest,
+        Octant::NorthNortheast,
+        Octant::EastNortheast,
+    ];
+
+    /// The two octants on either side of north.
+    pub const NORTH: [Octant; 2] = [Octant::NorthNorthwest, Octant::NorthNortheast];
+    /// The two octants on either side of east.
+    pub const EAST: [Octant; 2] = [Octant::EastNortheast, Octant::EastSoutheast];
+    /// The two octants on either side of south.
+    pub const SOUTH: [Octant; 2] = [Octant::SouthSoutheast, Octant::SouthSouthwest];
+    /// The two octants on either side of west.
+    pub const WEST: [Octant; 2] = [Octant::WestSouthwest, Octant::WestNorthwest];
+
+    /// The octant with the given number, or `None` if it's outside of 0-7.
+    pub fn from_index(index: i32) -> Option<Octant> {
+        match index {
+            0..=7 => Some(Octant::ALL[index as usize]),
+            _ => None,
+        }
+    }
+
+    /// The octant's number, from 0-7.
+    pub fn index(self) -> i32 {
+        self as i32
+    }
+}
+
+impl From<Octant> for i32 {
+    fn from(octant: Octant) -> Self {
+        octant.index()
+    }
+}
+
+/// Controls whether positions outside of a map's bounds block vision.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutOfBounds {
+    /// The map is surrounded by walls.
+    #[default]
+    Opaque,
+    /// Vision continues past the edges of the map, for maps surrounded by empty
+    /// space. Tiles outside the map are still never marked visible.
+    Transparent,
+}
+
+/// How the brightness of a light falls off with distance from its origin.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Falloff {
+    /// Brightness falls off linearly with distance.
+    #[default]
+    Linear,
+    /// Brightness falls off with the inverse square of the distance,
+    /// `1 / (1 + dist²)`.
+    InverseSquare,
+    /// Brightness falls off along a smoothstep curve, staying bright near the
+    /// origin and fading out smoothly towards the edge.
+    Smoothstep,
+}
+
+impl Falloff {
+    /// Returns the brightness from 0.0 to 1.0 of a tile at the given distance
+    /// from a light with the given range.
+    ///
+    /// Brightness is 1.0 at the origin. For [Falloff::Linear] and
+    /// [Falloff::Smoothstep] it reaches 0.0 one tile beyond `range`, so the tiles
+    /// on the edge of the range are still slightly lit, and a negative range
+    /// leaves everything but the origin dark. [Falloff::InverseSquare] only
+    /// depends on the distance and ignores `range`.
+    pub fn brightness(&self, dist: f32, range: f32) -> f32 {
+        let t = (dist / (range + 1.0).max(f32::EPSILON)).clamp(0.0, 1.0);
+        match self {
+            Falloff::Linear => 1.0 - t,
+            Falloff::InverseSquare => 1.0 / (1.0 + dist * dist),
+            Falloff::Smoothstep => {
+                let s = 1.0 - t;
+                s * s * (3.0 - 2.0 * s)
+            }
+        }
+    }
+}
+
+/// How the light from overlapping sources combines on a tile.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Blend {
+    /// The brightest source wins.
+    #[default]
+    Max,
+    /// Brightness from each source is added together, and can exceed 1.0.
+    Additive,
+    /// Brightness is combined as `1 - (1 - a) * (1 - b)`, which brightens
+    /// without exceeding 1.0.
+    Screen,
+}
+
+impl Blend {
+    /// Combine two brightness values.
+    pub fn blend(&self, a: f32, b: f32) -> f32 {
+        match self {
+            Blend::Max => a.max(b),
+            Blend::Additive => a + b,
+            Blend::Screen => 1.0 - (1.0 - a) * (1.0 - b),
+        }
+    }
+}
+
 /// A trait used by the fov algorithm to calculate the resulting fov.
+///
+/// Implementing this on your own map type lets the fov algorithm read opaque
+/// tiles and write visible tiles through a single mutable borrow of the map.
+///
+/// [VisibilityMap::is_opaque] and [VisibilityMap::set_visible] are only ever
+/// called with positions for which [VisibilityMap::is_in_bounds] returned true,
+/// so they can safely index into the map. The one exception is
+/// [fov::compute_unbounded], which never checks bounds. See [grid_index] and
+/// [centered_grid_index] for converting positions to indices.
+#[cfg(feature = "std")]
 pub trait VisibilityMap {
+    /// Whether the tile at `p` blocks light.
     fn is_opaque(&self, p: impl GridPoint) -> bool;
+    /// Whether `p` is inside the map. Tiles out of bounds always block light
+    /// and are never marked visible.
     fn is_in_bounds(&self, p: impl GridPoint) -> bool;
+    /// Called for each visible tile. Note this may be called more than once for
+    /// the same tile.
     fn set_visible(&mut self, p: impl GridPoint);
+    /// The distance used to clip the fov to its range.
+    ///
+    /// Note this is called with points relative to the fov origin in octant-local
+    /// space, so the distance should be symmetric across both axes.
     fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32;
+    /// Whether `b` is within `range` of `a`, used to clip the fov to its range.
+    ///
+    /// By default this compares [VisibilityMap::dist] to `range`, which can let
+    /// tiles just outside of very large ranges through due to floating point
+    /// rounding. Override it with an exact check like [RangeMetric::in_range]
+    /// if that matters.
+    fn in_range(&self, a: impl GridPoint, b: impl GridPoint, range: i32) -> bool {
+        self.dist(a, b) <= range as f32
+    }
 }
 
 #[derive(Default, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VisibilityPoint {
     pub visible: bool,
     pub opaque: bool,
 }
 
+#[cfg(feature = "std")]
 impl VisibilityMap for VisibilityMap2d {
     fn is_opaque(&self, p: impl GridPoint) -> bool {
         if self.in_bounds(p) {
@@ -71,8 +419,13 @@ impl VisibilityMap for VisibilityMap2d {
     fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
         Vec2::distance(a.as_vec2(), b.as_vec2())
     }
+
+    fn in_range(&self, a: impl GridPoint, b: impl GridPoint, range: i32) -> bool {
+        RangeMetric::Euclidean.in_range(a, b, range)
+    }
 }
 
+#[cfg(feature = "std")]
 pub trait VisibilityMapUtility {
     fn toggle_opaque(&mut self, p: impl GridPoint);
     fn toggle_visible(&mut self, p: impl GridPoint);
@@ -80,6 +433,7 @@ pub trait VisibilityMapUtility {
     fn clear_visible(&mut self);
 }
 
+#[cfg(feature = "std")]
 impl VisibilityMapUtility for VisibilityMap2d {
     fn toggle_opaque(&mut self, p: impl GridPoint) {
         let i = self.pos_to_index(p);
@@ -102,332 +456,2611 @@ impl VisibilityMapUtility for VisibilityMap2d {
     }
 }
 
+/// Converts a position into a `y * width + x` index, or `None` if it lies outside
+/// of a map of the given size starting at `[0,0]`.
+#[cfg(feature = "std")]
+pub fn grid_index(p: impl GridPoint, width: usize, height: usize) -> Option<usize> {
+    let p = p.as_ivec2();
+    (p.x >= 0 && p.y >= 0 && (p.x as usize) < width && (p.y as usize) < height)
+        .then(|| p.y as usize * width + p.x as usize)
+}
+
+/// Converts a position into a `y * width + x` index, or `None` if it lies outside
+/// of a map of the given size centered on `[0,0]`.
+///
+/// The map covers `-width / 2..width - width / 2` along the x axis, and likewise
+/// for y, matching [Grid::world_to_grid].
+#[cfg(feature = "std")]
+pub fn centered_grid_index(p: impl GridPoint, width: usize, height: usize) -> Option<usize> {
+    let offset = IVec2::new((width / 2) as i32, (height / 2) as i32);
+    grid_index(p.as_ivec2() + offset, width, height)
+}
+
 /// Module containing the compute function.
-pub mod fov {
-    use glam::IVec2;
+#[cfg(feature = "std")]
+pub mod fov;
+pub mod integer;
+#[cfg(feature = "std")]
+pub mod internal;
 
-    use crate::{GridPoint, VisibilityMap};
+#[cfg(all(test, feature = "std"))]
+mod test {
 
-    /// Compute the fov in a map from the given position.
-    pub fn compute<T: VisibilityMap>(origin: impl GridPoint, range: i32, map: &mut T) {
-        let origin = origin.as_ivec2();
-        map.set_visible(origin);
+    use crate::*;
 
-        for octant in 0..8 {
-            compute_octant(
-                octant,
-                origin,
-                range,
-                1,
-                Slope { x: 1, y: 1 },
-                Slope { x: 1, y: 0 },
-                map,
-            )
-        }
+    #[test]
+    fn test_fov() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[0, 1]].opaque = true;
+        map[[1, 0]].opaque = true;
+        fov::compute([0, 0], 5, &mut map);
+
+        assert!(map[[0, 0]].visible);
+
+        assert!(map[[0, 1]].visible);
+        assert!(!map[[0, 2]].visible);
+
+        assert!(map[[1, 0]].visible);
+        assert!(!map[[2, 0]].visible);
     }
 
-    fn compute_octant<T: VisibilityMap>(
-        octant: i32,
-        origin: IVec2,
-        range: i32,
-        x: i32,
-        mut top: Slope,
-        mut bottom: Slope,
-        map: &mut T,
-    ) {
-        for x in x..=range {
-            let y_coords = compute_y_coordinate(octant, origin, x, map, &mut top, &mut bottom);
-
-            let top_y = y_coords.x;
-            let bottom_y = y_coords.y;
-
-            if !compute_visiblity(
-                top_y,
-                bottom_y,
-                range,
-                octant,
-                origin,
-                x,
-                map,
-                &mut top,
-                &mut bottom,
-            ) {
-                break;
-            }
-        }
+    #[test]
+    fn test_range_zero() {
+        let mut map = VisibilityMap2d::default([10, 10]);
+        fov::compute([5, 5], 0, &mut map);
+        assert!(map[[5, 5]].visible);
+        assert_eq!(map.iter().filter(|p| p.visible).count(), 1);
+
+        map.clear_visible();
+        fov::compute([5, 5], -3, &mut map);
+        assert_eq!(map.iter().filter(|p| p.visible).count(), 1);
+
+        assert_eq!(fov::compute_tiles([5, 5], 0, &map), vec![IVec2::new(5, 5)]);
     }
 
-    fn compute_y_coordinate<T: VisibilityMap>(
-        octant: i32,
-        origin: IVec2,
-        x: i32,
-        map: &mut T,
-        top: &mut Slope,
-        bottom: &mut Slope,
-    ) -> IVec2 {
-        let mut top_y;
-        if top.x == 1 {
-            top_y = x;
-        } else {
-            top_y = ((x * 2 - 1) * top.y + top.x) / (top.x * 2);
+    #[test]
+    fn test_tiny_grids() {
+        let visible = |w: i32, h: i32, origin: [i32; 2], range: i32| {
+            let mut map = VisibilityMap2d::default([w, h]);
+            fov::compute(origin, range, &mut map);
+            let tiles: Vec<_> = map
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| p.visible)
+                .map(|(i, _)| i)
+                .collect();
+            tiles
+        };
 
-            if blocks_light(x, top_y, octant, origin, map) {
-                if top.greater_or_equal(top_y * 2 + 1, x * 2)
-                    && !blocks_light(x, top_y + 1, octant, origin, map)
-                {
-                    top_y += 1;
-                }
-            } else {
-                let mut ax = x * 2;
-                if blocks_light(x + 1, top_y + 1, octant, origin, map) {
-                    ax += 1;
-                }
-                if top.greater(top_y * 2 + 1, ax) {
-                    top_y += 1;
-                }
-            }
-        }
+        assert_eq!(visible(1, 1, [0, 0], 5), vec![0]);
+        assert_eq!(visible(2, 2, [0, 0], 5), vec![0, 1, 2, 3]);
+        assert_eq!(visible(2, 2, [1, 1], 1), vec![1, 2, 3]);
+        assert_eq!(visible(2, 2, [1, 1], 2), vec![0, 1, 2, 3]);
+        assert_eq!(visible(1, 10, [0, 4], 3), (1..=7).collect::<Vec<_>>());
+        assert_eq!(visible(10, 1, [4, 0], 3), (1..=7).collect::<Vec<_>>());
+        assert_eq!(visible(1, 10, [0, 0], 20), (0..10).collect::<Vec<_>>());
+        assert_eq!(visible(10, 1, [9, 0], 20), (0..10).collect::<Vec<_>>());
 
-        let mut bottom_y;
-        if bottom.y == 0 {
-            bottom_y = 0;
-        } else {
-            bottom_y = ((x * 2 - 1) * bottom.y + bottom.x) / (bottom.x * 2);
+        let mut map = VisibilityMap2d::default([1, 10]);
+        map[[0, 6]].opaque = true;
+        fov::compute([0, 4], 5, &mut map);
+        assert!(map[[0, 6]].visible);
+        assert!(!map[[0, 7]].visible);
+        assert!(map[[0, 0]].visible);
+    }
 
-            if bottom.greater_or_equal(bottom_y * 2 + 1, x * 2)
-                && blocks_light(x, bottom_y, octant, origin, map)
-                && !blocks_light(x, bottom_y + 1, octant, origin, map)
-            {
-                bottom_y += 1;
-            }
+    #[test]
+    fn test_reusable_fov() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[17, 16]].opaque = true;
+
+        let mut fov = fov::Fov::new();
+        for origin in [[15, 15], [3, 4], [15, 15]] {
+            let mut tiles = Vec::new();
+            fov.compute(origin, 8, &map, |p| tiles.push(p));
+            tiles.sort_unstable_by_key(|p| (p.y, p.x));
+            assert_eq!(fov::compute_tiles(origin, 8, &map), tiles);
         }
-        IVec2::new(top_y, bottom_y)
     }
 
-    #[allow(clippy::too_many_arguments)]
-    fn compute_visiblity<T: VisibilityMap>(
-        top_y: i32,
-        bottom_y: i32,
-        range: i32,
-        octant: i32,
-        origin: IVec2,
-        x: i32,
-        map: &mut T,
-        top: &mut Slope,
-        bottom: &mut Slope,
-    ) -> bool {
-        let mut was_opaque = -1;
-
-        for y in (bottom_y..=top_y).rev() {
-            if range < 0 || map.dist(IVec2::ZERO, IVec2::new(x, y)) <= range as f32 {
-                let is_opaque = blocks_light(x, y, octant, origin, map);
-
-                // Less symmetrical
-                // let is_visible = is_opaque ||
-                // (
-                //     (y != top_y || top.greater(y * 4 - 1, x * 4 + 1)) &&
-                //     (y != bottom_y || bottom.less(y * 4 + 1, x * 4 - 1))
-                // );
-
-                // Better symmetry
-                let is_visible = is_opaque || // Remove is_opaque check for full symmetry but more artifacts in hallways
-                (
-                    (y != top_y || top.greater_or_equal(y, x)) &&
-                    (y != bottom_y || bottom.less_or_equal(y, x))
-                );
+    #[test]
+    fn test_origin_out_of_bounds() {
+        let map = VisibilityMap2d::default([10, 10]);
+        let mut tiles = Vec::new();
+        fov::compute_attenuated([-1, 5], 5, &map, |p, _| tiles.push(p));
 
-                if is_visible {
-                    set_visible(x, y, octant, origin, map);
-                }
+        assert!(tiles.iter().all(|p| map.in_bounds(*p)));
+        assert!(tiles.contains(&IVec2::new(0, 5)));
+        assert!(tiles.contains(&IVec2::new(3, 7)));
+    }
 
-                if x != range {
-                    if is_opaque {
-                        if was_opaque == 0 {
-                            let mut nx = x * 2;
-                            let ny = y * 2 + 1;
-                            if blocks_light(x, y + 1, octant, origin, map) {
-                                nx -= 1;
-                            }
-                            if top.greater(ny, nx) {
-                                if y == bottom_y {
-                                    *bottom = Slope { y: ny, x: nx };
-                                    break;
-                                } else {
-                                    compute_octant(
-                                        octant,
-                                        origin,
-                                        range,
-                                        x + 1,
-                                        top.clone(),
-                                        Slope { y: ny, x: nx },
-                                        map,
-                                    );
-                                }
-                            } else if y == bottom_y {
-                                return false;
-                            }
-                        }
-                        was_opaque = 1;
-                    } else {
-                        if was_opaque > 0 {
-                            let mut nx = x * 2;
-                            let ny = y * 2 + 1;
-                            if blocks_light(x + 1, y + 1, octant, origin, map) {
-                                nx += 1;
-                            }
-                            if bottom.greater_or_equal(ny, nx) {
-                                return false;
-                            }
-                            *top = Slope { y: ny, x: nx };
-                        }
-                        was_opaque = 0;
-                    }
-                }
-            }
+    #[test]
+    fn test_origin_types() {
+        let mut expected = VisibilityMap2d::default([30, 30]);
+        fov::compute([10, 12], 4, &mut expected);
+
+        let origins = [
+            IVec2::new(10, 12),
+            IVec2::from([10, 12]),
+            IVec2::from((10, 12)),
+            UVec2::new(10, 12).as_ivec2(),
+            UVec2::from((10_u32, 12_u32)).as_ivec2(),
+        ];
+        for origin in origins {
+            let mut map = VisibilityMap2d::default([30, 30]);
+            fov::compute(origin, 4, &mut map);
+            assert!(map
+                .iter()
+                .map(|p| p.visible)
+                .eq(expected.iter().map(|p| p.visible)));
         }
 
-        was_opaque == 0
+        // Wraps to a negative, out of bounds origin
+        let mut map = VisibilityMap2d::default([30, 30]);
+        fov::compute(UVec2::new(u32::MAX, 0).as_ivec2(), 4, &mut map);
+        assert!(map[[3, 0]].visible);
+        assert!(!map[[4, 0]].visible);
     }
 
-    fn blocks_light<T: VisibilityMap>(
-        x: i32,
-        y: i32,
-        octant: i32,
-        origin: IVec2,
-        map: &mut T,
-    ) -> bool {
-        let (mut nx, mut ny) = origin.into();
-        match octant {
-            0 => {
-                nx += x;
-                ny -= y;
-            }
-            1 => {
-                nx += y;
-                ny -= x;
-            }
-            2 => {
-                nx -= y;
-                ny -= x;
-            }
-            3 => {
-                nx -= x;
-                ny -= y;
-            }
-            4 => {
-                nx -= x;
-                ny += y;
-            }
-            5 => {
-                nx -= y;
-                ny += x;
-            }
-            6 => {
-                nx += y;
-                ny += x;
-            }
-            7 => {
-                nx += x;
-                ny += y;
-            }
-            _ => {}
-        }
-        let p = IVec2::new(nx, ny);
-        if !map.is_in_bounds(p) {
-            return true;
-        }
-        map.is_opaque(IVec2::new(nx, ny))
+    #[test]
+    fn test_with_origin() {
+        let map = VisibilityMap2d::default([30, 30]);
+        let mut tiles = Vec::new();
+        fov::compute_with_origin([10, 10], 4, &map, |p, o| tiles.push((p, o)));
+
+        assert_eq!(tiles[0], (IVec2::new(10, 10), true));
+        assert!(tiles[1..]
+            .iter()
+            .all(|(p, o)| !o && *p != IVec2::new(10, 10)));
     }
 
-    fn set_visible<T: VisibilityMap>(x: i32, y: i32, octant: i32, origin: IVec2, map: &mut T) {
-        let (mut nx, mut ny) = origin.into();
-        match octant {
-            0 => {
-                nx += x;
-                ny -= y;
-            }
-            1 => {
-                nx += y;
-                ny -= x;
-            }
-            2 => {
-                nx -= y;
-                ny -= x;
-            }
-            3 => {
-                nx -= x;
-                ny -= y;
-            }
-            4 => {
-                nx -= x;
-                ny += y;
-            }
-            5 => {
-                nx -= y;
-                ny += x;
-            }
-            6 => {
-                nx += y;
-                ny += x;
-            }
-            7 => {
-                nx += x;
-                ny += y;
+    #[test]
+    fn test_without_origin() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        fov::compute_without_origin([10, 10], 4, &mut map);
+        assert!(!map[[10, 10]].visible);
+        assert!(map[[10, 11]].visible);
+        assert!(map[[14, 10]].visible);
+
+        let mut tiles = Vec::new();
+        fov::FovConfig::new(4)
+            .include_origin(false)
+            .compute_with([10, 10], &map, |p| tiles.push(p));
+        assert!(!tiles.contains(&IVec2::new(10, 10)));
+        assert!(tiles.contains(&IVec2::new(10, 11)));
+    }
+
+    #[test]
+    fn test_opaque_origin() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[16, 15]].opaque = true;
+        let open = fov::compute_tiles([15, 15], 6, &map);
+
+        map[[15, 15]].opaque = true;
+        let origin = vec![IVec2::new(15, 15)];
+        assert_eq!(fov::compute_tiles([15, 15], 6, &map), origin);
+        assert_eq!(fov::iter([15, 15], 6, &map).collect::<Vec<_>>(), origin);
+        assert!(!fov::is_visible_from([15, 15], [14, 15], 6, &map));
+
+        fov::compute([15, 15], 6, &mut map);
+        assert_eq!(map.iter().filter(|p| p.visible).count(), 1);
+        assert!(map[[15, 15]].visible);
+
+        let config = fov::FovConfig::new(6);
+        let tiles = |config: fov::FovConfig| {
+            let mut tiles = Vec::new();
+            config.compute_with([15, 15], &map, |p| tiles.push(p));
+            tiles.sort_by_key(|p| (p.x, p.y));
+            tiles.dedup();
+            tiles
+        };
+        assert_eq!(tiles(config), origin);
+
+        // The wall to the east is seen, but nothing past it or the other
+        // neighbours
+        let adjacent = tiles(config.opaque_origin(OpaqueOrigin::Adjacent));
+        assert_eq!(adjacent.len(), 9);
+        assert!(adjacent.contains(&IVec2::new(16, 15)));
+        assert!(adjacent.contains(&IVec2::new(14, 14)));
+        assert!(adjacent
+            .iter()
+            .all(|p| (*p - IVec2::new(15, 15)).abs().max_element() <= 1));
+
+        // Diagonal neighbours are out of range 1
+        let adjacent = tiles(config.range(1).opaque_origin(OpaqueOrigin::Adjacent));
+        assert_eq!(adjacent.len(), 5);
+
+        let mut open = open;
+        open.sort_by_key(|p| (p.x, p.y));
+        assert_eq!(tiles(config.opaque_origin(OpaqueOrigin::Transparent)), open);
+    }
+
+    #[test]
+    fn test_opaque_origin_zero_range() {
+        let mut map = VisibilityMap2d::default([10, 10]);
+        map[[5, 5]].opaque = true;
+
+        assert_eq!(fov::compute_tiles([5, 5], 0, &map), vec![IVec2::new(5, 5)]);
+
+        let mut tiles = Vec::new();
+        fov::FovConfig::new(0).compute_with([5, 5], &map, |p| tiles.push(p));
+        assert_eq!(tiles, vec![IVec2::new(5, 5)]);
+
+        tiles.clear();
+        fov::FovConfig::new(0)
+            .include_origin(false)
+            .compute_with([5, 5], &map, |p| tiles.push(p));
+        assert!(tiles.is_empty());
+    }
+
+    #[test]
+    fn test_wh() {
+        let mut map = VisibilityMap2d::default([20, 10]);
+        map[[12, 5]].opaque = true;
+
+        let mut tiles = Vec::new();
+        fov::compute_wh(
+            IVec2::new(10, 5),
+            6,
+            20,
+            10,
+            |p| {
+                assert!(map.in_bounds(p));
+                map[p].opaque
+            },
+            |p| tiles.push(p),
+        );
+        tiles.sort_unstable_by_key(|p| (p.y, p.x));
+        tiles.dedup();
+        assert_eq!(tiles, fov::compute_tiles([10, 5], 6, &map));
+
+        let mut huge = Vec::new();
+        fov::compute_wh(
+            IVec2::new(10, 5),
+            i32::MAX,
+            20,
+            10,
+            |p| map[p].opaque,
+            |p| huge.push(p),
+        );
+        huge.sort_unstable_by_key(|p| (p.y, p.x));
+        huge.dedup();
+        assert_eq!(huge, fov::compute_tiles([10, 5], 100, &map));
+        assert_eq!(
+            fov::compute_tiles([10, 5], i32::MAX, &map),
+            fov::compute_tiles([10, 5], 100, &map)
+        );
+    }
+
+    #[test]
+    fn test_try_compute_wh() {
+        let mut tiles = Vec::new();
+        let result = fov::try_compute_wh(IVec2::new(0, 0), 5, 0, 10, |_| false, |p| tiles.push(p));
+        assert_eq!(result, Err(fov::FovError::EmptyGrid));
+
+        let result =
+            fov::try_compute_wh(IVec2::new(10, 2), 5, 10, 10, |_| false, |p| tiles.push(p));
+        assert_eq!(
+            result,
+            Err(fov::FovError::OriginOutOfBounds(IVec2::new(10, 2)))
+        );
+        assert!(tiles.is_empty());
+
+        let result = fov::try_compute_wh(IVec2::new(9, 2), 5, 10, 10, |_| false, |p| tiles.push(p));
+        assert_eq!(result, Ok(()));
+        assert!(tiles.contains(&IVec2::new(9, 2)));
+    }
+
+    #[test]
+    fn test_into_slice() {
+        let mut map = VisibilityMap2d::default([20, 10]);
+        map[[12, 5]].opaque = true;
+
+        let mut visible = vec![true; 20 * 10];
+        fov::compute_into_slice([10, 5], 4, 20, 10, &map, &mut visible);
+
+        fov::compute([10, 5], 4, &mut map);
+        assert!(map.iter().map(|p| p.visible).eq(visible.iter().copied()));
+        assert!(visible[5 * 20 + 12]);
+        assert!(!visible[5 * 20 + 13]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_into_slice_wrong_size() {
+        let map = VisibilityMap2d::default([20, 10]);
+        fov::compute_into_slice([10, 5], 4, 20, 10, &map, &mut [false; 20]);
+    }
+
+    #[test]
+    fn test_bool_grid_map() {
+        let mut map = BoolGridMap::new(30, 20);
+        map.set_opaque([16, 15], true);
+        fov::compute([15, 15], 5, &mut map);
+
+        assert!(map.is_visible([15, 15]));
+        assert!(map.is_visible([16, 15]));
+        assert!(!map.is_visible([17, 15]));
+        assert!(map.is_visible([15, 19]));
+        assert!(!map.is_visible([15, 20]));
+        assert!(!map.is_visible([-1, 15]));
+
+        map.clear_visible();
+        assert!(map.visible().iter().all(|v| !v));
+    }
+
+    #[test]
+    fn test_grid_index() {
+        assert_eq!(grid_index([0, 0], 10, 5), Some(0));
+        assert_eq!(grid_index([3, 2], 10, 5), Some(23));
+        assert_eq!(grid_index([-1, 2], 10, 5), None);
+        assert_eq!(grid_index([10, 2], 10, 5), None);
+        assert_eq!(grid_index([3, 5], 10, 5), None);
+
+        assert_eq!(centered_grid_index([-5, -2], 10, 5), Some(0));
+        assert_eq!(centered_grid_index([0, 0], 10, 5), Some(25));
+        assert_eq!(centered_grid_index([4, 2], 10, 5), Some(49));
+        assert_eq!(centered_grid_index([5, 0], 10, 5), None);
+        assert_eq!(centered_grid_index([0, -3], 10, 5), None);
+    }
+
+    #[test]
+    fn test_compute_as() {
+        let map = VisibilityMap2d::default([30, 30]);
+
+        let mut tuples: Vec<(i32, i32)> = Vec::new();
+        fov::compute_as([10, 10], 3, &map, |p| tuples.push(p));
+        assert!(tuples.contains(&(10, 13)));
+
+        let mut arrays = Vec::new();
+        fov::compute_as([10, 10], 3, &map, |p: [i32; 2]| arrays.push(p));
+        assert!(arrays.contains(&[13, 10]));
+        assert_eq!(tuples.len(), arrays.len());
+    }
+
+    #[test]
+    fn test_config() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[12, 12]].opaque = true;
+
+        // Matches the individual compute functions
+        let mut expected = map.clone();
+        fov::compute_with_metric([10, 10], 5, RangeMetric::Chebyshev, &mut expected);
+        fov::FovConfig::new(5)
+            .metric(RangeMetric::Chebyshev)
+            .compute([10, 10], &mut map);
+        assert!(map
+            .iter()
+            .map(|p| p.visible)
+            .eq(expected.iter().map(|p| p.visible)));
+
+        map.clear_visible();
+        let mut expected = map.clone();
+        fov::compute_cone([10, 10], 6, [1, 0], 45.0, &mut expected);
+        fov::FovConfig::new(6)
+            .cone([1, 0], 45.0)
+            .compute([10, 10], &mut map);
+        assert!(map
+            .iter()
+            .map(|p| p.visible)
+            .eq(expected.iter().map(|p| p.visible)));
+
+        // Combined
+        let mut tiles = Vec::new();
+        fov::FovConfig::new(6)
+            .metric(RangeMetric::Chebyshev)
+            .cone([1, 0], 45.0)
+            .min_range(2)
+            .compute_with([10, 10], &map, |p| tiles.push(p));
+        assert!(tiles.contains(&IVec2::new(16, 4)));
+        assert!(tiles.contains(&IVec2::new(12, 10)));
+        assert!(!tiles.contains(&IVec2::new(11, 10)));
+        assert!(!tiles.contains(&IVec2::new(10, 10)));
+        assert!(!tiles.contains(&IVec2::new(8, 10)));
+    }
+
+    #[test]
+    fn test_integer_matches_fov() {
+        let mut seed = 0x2545_f491_u32;
+        let mut rand = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed
+        };
+
+        for _ in 0..50 {
+            let mut map = VisibilityMap2d::default([40, 30]);
+            let density = rand() % 40;
+            map.iter_mut()
+                .for_each(|p| p.opaque = rand() % 100 < density);
+            let origin = IVec2::new((rand() % 40) as i32, (rand() % 30) as i32);
+            let range = (rand() % 25) as i32;
+
+            let mut expected = Vec::new();
+            fov::compute_wh(
+                origin,
+                range,
+                40,
+                30,
+                |p| map[p].opaque,
+                |p| expected.push(p.to_array()),
+            );
+            let mut tiles = Vec::new();
+            integer::compute(
+                origin.to_array(),
+                range,
+                40,
+                30,
+                |p| map[p].opaque,
+                |p| tiles.push(p),
+            );
+            for v in [&mut expected, &mut tiles] {
+                v.sort_unstable();
+                v.dedup();
             }
-            _ => {}
-        }
-        let p = IVec2::new(nx, ny);
-        if map.is_in_bounds(p) {
-            map.set_visible(p);
+            assert_eq!(tiles, expected, "{:?} {}", origin, range);
         }
     }
 
-    #[derive(Clone)]
-    struct Slope {
-        x: i32,
-        y: i32,
-    } // represents the slope Y/X as a rational number
+    #[test]
+    fn test_integer_matches_compute() {
+        let mut seed = 0x9e37_79b9_u32;
+        let mut rand = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed
+        };
 
-    impl Slope {
-        // this > y/x
-        pub fn greater(&self, y: i32, x: i32) -> bool {
-            self.y * x > self.x * y
-        }
+        for _ in 0..50 {
+            let mut map = VisibilityMap2d::default([40, 30]);
+            let density = rand() % 40;
+            map.iter_mut()
+                .for_each(|p| p.opaque = rand() % 100 < density);
+            let origin = IVec2::new((rand() % 40) as i32, (rand() % 30) as i32);
+            let range = (rand() % 25) as i32;
+
+            let mut visible = VisibilityMap2d::default([40, 30]);
+            integer::compute(
+                origin.to_array(),
+                range,
+                40,
+                30,
+                |p| map[p].opaque,
+                |p| visible[p].visible = true,
+            );
+            fov::compute(origin, range, &mut map);
 
-        // s >= y/x
-        pub fn greater_or_equal(&self, y: i32, x: i32) -> bool {
-            self.y * x >= self.x * y
+            assert!(
+                map.iter()
+                    .map(|p| p.visible)
+                    .eq(visible.iter().map(|p| p.visible)),
+                "{:?} {}",
+                origin,
+                range
+            );
         }
+    }
+
+    #[test]
+    fn test_chebyshev() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        fov::compute_with_metric([15, 15], 5, RangeMetric::Chebyshev, &mut map);
 
-        // s < y/x
-        //pub fn less(&self, y: i32, x: i32) -> bool {
-        //    self.y * x < self.x * y
-        //}
+        assert!(map[[20, 20]].visible);
+        assert!(map[[10, 10]].visible);
+        assert!(!map[[21, 20]].visible);
+        assert!(!map[[15, 21]].visible);
 
-        pub fn less_or_equal(&self, y: i32, x: i32) -> bool {
-            self.y * x <= self.x * y
-        } // this <= y/x
+        let mut map = VisibilityMap2d::default([30, 30]);
+        fov::compute([15, 15], 5, &mut map);
+        assert!(!map[[20, 20]].visible);
     }
-}
 
-#[cfg(test)]
-mod test {
+    #[test]
+    fn test_manhattan() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        fov::compute_with_metric([15, 15], 5, RangeMetric::Manhattan, &mut map);
 
-    use crate::*;
+        assert!(map[[20, 15]].visible);
+        assert!(map[[15, 10]].visible);
+        assert!(map[[18, 13]].visible);
+        assert!(map[[12, 17]].visible);
+        assert!(!map[[18, 12]].visible);
+        assert!(!map[[11, 17]].visible);
+    }
 
     #[test]
-    fn test_fov() {
+    fn test_within_range() {
+        let mut seed = 0x9e37_79b9_u32;
+        let mut rand = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed
+        };
+
+        for _ in 0..50 {
+            let mut map = VisibilityMap2d::default([40, 40]);
+            let density = rand() % 30;
+            map.iter_mut()
+                .for_each(|p| p.opaque = rand() % 100 < density);
+            let origin = IVec2::new((rand() % 40) as i32, (rand() % 40) as i32);
+            let range = (rand() % 20) as i32;
+
+            for metric in [
+                RangeMetric::Euclidean,
+                RangeMetric::Chebyshev,
+                RangeMetric::Manhattan,
+            ] {
+                map.clear_visible();
+                fov::compute_with_metric(origin, range, metric, &mut map);
+                for i in 0..map.len() {
+                    let p = map.index_to_pos(i);
+                    let d = (p - origin).abs();
+                    let within = match metric {
+                        RangeMetric::Euclidean => d.x * d.x + d.y * d.y <= range * range,
+                        RangeMetric::Chebyshev => d.max_element() <= range,
+                        RangeMetric::Manhattan => d.x + d.y <= range,
+                    };
+                    assert!(!map[p].visible || within, "{:?} {:?}", metric, p);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_visible_with_metric() {
         let mut map = VisibilityMap2d::default([30, 30]);
-        map[[0, 1]].opaque = true;
-        map[[1, 0]].opaque = true;
-        fov::compute([0, 0], 5, &mut map);
+        map[[13, 15]].opaque = true;
+        let metric = RangeMetric::Chebyshev;
 
-        assert!(map[[0, 0]].visible);
+        assert!(fov::is_visible_with_metric(
+            [15, 15],
+            [20, 20],
+            5,
+            metric,
+            &map
+        ));
+        assert!(!fov::is_visible_from([15, 15], [20, 20], 5, &map));
+        assert!(!fov::is_visible_with_metric(
+            [15, 15],
+            [21, 15],
+            5,
+            metric,
+            &map
+        ));
+        assert!(!fov::is_visible_with_metric(
+            [15, 15],
+            [11, 15],
+            5,
+            metric,
+            &map
+        ));
 
-        assert!(map[[0, 1]].visible);
-        assert!(!map[[0, 2]].visible);
+        fov::compute_with_metric([15, 15], 5, metric, &mut map);
+        for x in 8..23 {
+            for y in 8..23 {
+                let visible = fov::is_visible_with_metric([15, 15], [x, y], 5, metric, &map);
+                assert_eq!(visible, map[[x, y]].visible, "{} {}", x, y);
+            }
+        }
+    }
 
-        assert!(map[[1, 0]].visible);
-        assert!(!map[[2, 0]].visible);
+    #[test]
+    fn test_ellipse() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        fov::compute_ellipse([15, 15], 8, 3, &mut map);
+
+        assert!(map[[23, 15]].visible);
+        assert!(map[[7, 15]].visible);
+        assert!(!map[[24, 15]].visible);
+        assert!(map[[15, 18]].visible);
+        assert!(map[[15, 12]].visible);
+        assert!(!map[[15, 19]].visible);
+        assert!(!map[[15, 11]].visible);
+    }
+
+    #[test]
+    fn test_box() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[13, 15]].opaque = true;
+        fov::compute_box([15, 15], 2, 1, 4, 6, &mut map);
+
+        assert!(map[[21, 17]].visible);
+        assert!(map[[11, 14]].visible);
+        assert!(!map[[22, 15]].visible);
+        assert!(!map[[15, 18]].visible);
+        assert!(!map[[15, 13]].visible);
+        assert!(!map[[10, 15]].visible);
+
+        // Walls inside the box still cast shadows
+        assert!(!map[[11, 15]].visible);
+    }
+
+    #[test]
+    fn test_ring() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[16, 15]].opaque = true;
+        fov::compute_ring([15, 15], 3, 6, &mut map);
+
+        assert!(!map[[15, 15]].visible);
+        assert!(!map[[15, 17]].visible);
+        assert!(!map[[16, 15]].visible);
+        assert!(map[[15, 18]].visible);
+        assert!(map[[15, 21]].visible);
+        assert!(!map[[15, 22]].visible);
+
+        // The wall inside the ring still casts a shadow
+        assert!(!map[[19, 15]].visible);
+    }
+
+    #[test]
+    fn test_disc() {
+        let mut fov_map = VisibilityMap2d::default([20, 20]);
+        let mut disc_map = VisibilityMap2d::default([20, 20]);
+        fov::compute([3, 4], 7, &mut fov_map);
+        fov::compute_disc([3, 4], 7, &mut disc_map);
+        assert!(fov_map
+            .iter()
+            .map(|p| p.visible)
+            .eq(disc_map.iter().map(|p| p.visible)));
+
+        // Walls are ignored
+        disc_map[[5, 4]].opaque = true;
+        fov::compute_disc([3, 4], 7, &mut disc_map);
+        assert!(disc_map[[7, 4]].visible);
+    }
+
+    #[test]
+    fn test_f32_range() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        fov::compute_f32([15, 15], 4.5, &mut map);
+        assert!(map[[18, 18]].visible);
+        assert!(map[[19, 15]].visible);
+        assert!(!map[[20, 15]].visible);
+
+        let mut map = VisibilityMap2d::default([30, 30]);
+        fov::compute_f32([15, 15], 4.0, &mut map);
+        assert!(!map[[18, 18]].visible);
+        assert!(map[[19, 15]].visible);
+    }
+
+    #[test]
+    fn test_per_octant() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[20, 16]].opaque = true;
+        // A spotlight facing east
+        let mut ranges = [2; 8];
+        ranges[Octant::EastSoutheast.index() as usize] = 8;
+        ranges[Octant::EastNortheast.index() as usize] = 8;
+        fov::compute_per_octant([15, 15], ranges, &mut map);
+
+        assert!(map[[23, 15]].visible);
+        assert!(map[[22, 13]].visible);
+        assert!(!map[[24, 15]].visible);
+        // Walls still cast shadows
+        assert!(!map[[22, 16]].visible);
+        assert!(map[[15, 17]].visible);
+        assert!(!map[[15, 18]].visible);
+        assert!(map[[13, 15]].visible);
+        assert!(!map[[12, 15]].visible);
+    }
+
+    #[test]
+    fn test_range_bound() {
+        let ring = |metric, bound| {
+            let mut map = VisibilityMap2d::default([21, 21]);
+            fov::compute_with_bound([10, 10], 5, metric, bound, &mut map);
+            map
+        };
+
+        let inclusive = ring(RangeMetric::Euclidean, RangeBound::Inclusive);
+        let exclusive = ring(RangeMetric::Euclidean, RangeBound::Exclusive);
+        for p in [[15, 10], [14, 13], [13, 14], [10, 5], [7, 6]] {
+            assert!(inclusive[p].visible);
+            assert!(!exclusive[p].visible);
+        }
+        for p in [[14, 12], [12, 14], [14, 11], [11, 6]] {
+            assert!(inclusive[p].visible);
+            assert!(exclusive[p].visible);
+        }
+        let count = |map: &VisibilityMap2d| map.iter().filter(|p| p.visible).count();
+        // 12 tiles lie exactly on the edge of a radius 5 circle
+        assert_eq!(count(&inclusive) - count(&exclusive), 12);
+
+        let inclusive = ring(RangeMetric::Chebyshev, RangeBound::Inclusive);
+        let exclusive = ring(RangeMetric::Chebyshev, RangeBound::Exclusive);
+        assert!(inclusive[[15, 15]].visible);
+        assert!(!exclusive[[15, 15]].visible);
+        assert!(exclusive[[14, 14]].visible);
+        assert_eq!(count(&exclusive), 81);
+    }
+
+    #[test]
+    fn test_sight_ranges() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[18, 15]].opaque = true;
+        map[[15, 16]].opaque = true;
+        map[[13, 13]].opaque = true;
+
+        let ranges = fov::sight_ranges([15, 15], 6, &map);
+        assert_eq!(ranges, [2, 4, 6, 1, 6, 4, 0, 4]);
+
+        // Clipped by the edge of the map
+        assert_eq!(fov::sight_ranges([1, 1], 6, &map)[4], 1);
+    }
+
+    /// A grid map with a square range, which still reports euclidean
+    /// distances.
+    struct Square(VisibilityMap2d);
+
+    impl VisibilityMap for Square {
+        fn is_opaque(&self, p: impl GridPoint) -> bool {
+            self.0.is_opaque(p)
+        }
+
+        fn is_in_bounds(&self, p: impl GridPoint) -> bool {
+            self.0.is_in_bounds(p)
+        }
+
+        fn set_visible(&mut self, _: impl GridPoint) {}
+
+        fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+            self.0.dist(a, b)
+        }
+
+        fn in_range(&self, a: impl GridPoint, b: impl GridPoint, range: i32) -> bool {
+            RangeMetric::Chebyshev.in_range(a, b, range)
+        }
+    }
+
+    #[test]
+    fn test_tiered() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[10, 13]].opaque = true;
+
+        let mut tiles = Vec::new();
+        fov::compute_tiered([10, 10], &[1, 3, 6], &map, |p, t| tiles.push((p, t)));
+        let tier = |p: [i32; 2]| {
+            tiles
+                .iter()
+                .find(|(t, _)| *t == IVec2::from(p))
+                .map(|(_, t)| *t)
+        };
+        assert_eq!(tier([10, 10]), Some(0));
+        assert_eq!(tier([11, 10]), Some(0));
+        assert_eq!(tier([12, 10]), Some(1));
+        assert_eq!(tier([16, 10]), Some(2));
+        assert_eq!(tier([17, 10]), None);
+        assert_eq!(tier([10, 14]), None);
+
+        let mut count = 0;
+        fov::compute_tiered([10, 10], &[], &map, |_, _| count += 1);
+        assert_eq!(count, 0);
+
+        // Corners are in range of the square tiers, even though they're
+        // further away by euclidean distance
+        let map = Square(VisibilityMap2d::default([30, 30]));
+        let mut tiles = Vec::new();
+        fov::compute_tiered([10, 10], &[2, 4], &map, |p, t| tiles.push((p, t)));
+        assert!(tiles.contains(&(IVec2::new(12, 12), 0)));
+        assert!(tiles.contains(&(IVec2::new(14, 14), 1)));
+        assert!(tiles.contains(&(IVec2::new(6, 14), 1)));
+        tiles.sort_unstable_by_key(|(p, _)| p.to_array());
+        tiles.dedup();
+        assert_eq!(tiles.len(), 81);
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(debug_assertions)]
+    fn test_tiered_unsorted() {
+        let map = VisibilityMap2d::default([30, 30]);
+        fov::compute_tiered([10, 10], &[6, 3], &map, |_, _| {});
+    }
+
+    #[test]
+    fn test_large_range_boundary() {
+        // Past ~4096 tiles an f32 distance can't tell these apart
+        assert!(RangeMetric::Euclidean.in_range([0, 0], [4097, 0], 4097));
+        assert!(!RangeMetric::Euclidean.in_range([0, 0], [4097, 1], 4097));
+
+        let mut map = BoolGridMap::new(5000, 2);
+        fov::compute([0, 0], 4097, &mut map);
+        assert!(map.is_visible([4097, 0]));
+        assert!(map.is_visible([4096, 1]));
+        assert!(!map.is_visible([4097, 1]));
+    }
+
+    /// A thin band of tiles along the diagonal from `[0,0]` to `[len,len]`, with
+    /// a single wall.
+    struct DiagonalBand {
+        len: i32,
+        wall: IVec2,
+        visible: Vec<IVec2>,
+    }
+
+    impl VisibilityMap for DiagonalBand {
+        fn is_opaque(&self, p: impl GridPoint) -> bool {
+            p.as_ivec2() == self.wall
+        }
+
+        fn is_in_bounds(&self, p: impl GridPoint) -> bool {
+            let p = p.as_ivec2();
+            (0..=self.len).contains(&p.x) && (0..=self.len).contains(&p.y) && (p.x - p.y).abs() <= 2
+        }
+
+        fn set_visible(&mut self, p: impl GridPoint) {
+            self.visible.push(p.as_ivec2());
+        }
+
+        fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+            RangeMetric::Euclidean.dist(a, b)
+        }
+
+        fn in_range(&self, a: impl GridPoint, b: impl GridPoint, range: i32) -> bool {
+            RangeMetric::Euclidean.in_range(a, b, range)
+        }
+    }
+
+    #[test]
+    fn test_large_range_slopes() {
+        // The wall's shadow starts at a slope of 60003 / 60000, and comparing
+        // it against columns tens of thousands of tiles out overflows i32
+        let mut map = DiagonalBand {
+            len: 60000,
+            wall: IVec2::new(30000, 30001),
+            visible: Vec::new(),
+        };
+        fov::compute([0, 0], 90000, &mut map);
+        let visible: std::collections::HashSet<_> = map.visible.into_iter().collect();
+
+        for i in [1, 1000, 30000, 50000, 59999, 60000] {
+            assert!(visible.contains(&IVec2::new(i, i)), "{}", i);
+            assert!(visible.contains(&IVec2::new(i, i - 1)), "{}", i);
+        }
+        assert!(visible.contains(&IVec2::new(30000, 30001)));
+        assert!(!visible.contains(&IVec2::new(45000, 45002)));
+        assert!(!visible.contains(&IVec2::new(59998, 60000)));
+    }
+
+    #[test]
+    fn test_octants() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        fov::compute_octants([15, 15], 5, &[6, 7], &mut map);
+
+        assert!(map[[15, 15]].visible);
+        assert!(map[[20, 15]].visible);
+        assert!(map[[19, 16]].visible);
+        assert!(map[[16, 19]].visible);
+        assert!(map[[15, 20]].visible);
+        assert!(!map[[14, 19]].visible);
+        assert!(!map[[19, 14]].visible);
+        assert!(!map[[10, 15]].visible);
+    }
+
+    #[test]
+    fn test_cardinal() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[15, 18]].opaque = true;
+        fov::compute_cardinal([15, 15], 5, &mut map);
+
+        assert!(map[[15, 15]].visible);
+        assert!(map[[20, 15]].visible);
+        assert!(map[[10, 15]].visible);
+        assert!(map[[15, 10]].visible);
+        assert!(map[[15, 18]].visible);
+        assert!(!map[[15, 19]].visible);
+        assert!(!map[[16, 16]].visible);
+        assert_eq!(19, map.iter().filter(|p| p.visible).count());
+    }
+
+    #[test]
+    fn test_compute_octant() {
+        use internal::Slope;
+
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[12, 12]].opaque = true;
+        map[[8, 13]].opaque = true;
+        let mut expected = map.clone();
+        fov::compute([10, 10], 6, &mut expected);
+
+        map[[10, 10]].visible = true;
+        for octant in 0..8 {
+            internal::compute_octant(
+                [10, 10],
+                6,
+                octant,
+                Slope::new(1, 1),
+                Slope::new(0, 1),
+                &mut map,
+            );
+        }
+        assert!(map
+            .iter()
+            .map(|p| p.visible)
+            .eq(expected.iter().map(|p| p.visible)));
+
+        // Only the lower half of the east-north-east octant
+        map.clear_visible();
+        internal::compute_octant([10, 10], 6, 7, Slope::new(1, 2), Slope::new(0, 1), &mut map);
+        assert!(map[[16, 10]].visible);
+        assert!(map[[14, 11]].visible);
+        assert!(!map[[14, 13]].visible);
+        assert!(!map[[10, 10]].visible);
+    }
+
+    #[test]
+    fn test_octant_names() {
+        for (i, octant) in Octant::ALL.iter().enumerate() {
+            assert_eq!(octant.index(), i as i32);
+            assert_eq!(Octant::from_index(i as i32), Some(*octant));
+        }
+        assert_eq!(Octant::from_index(8), None);
+
+        let mut named = VisibilityMap2d::default([30, 30]);
+        fov::compute_octants([15, 15], 5, &Octant::NORTH, &mut named);
+        let mut numbered = VisibilityMap2d::default([30, 30]);
+        fov::compute_octants([15, 15], 5, &[5, 6], &mut numbered);
+        assert!(named
+            .iter()
+            .map(|p| p.visible)
+            .eq(numbered.iter().map(|p| p.visible)));
+        assert!(named[[15, 20]].visible);
+        assert!(!named[[15, 10]].visible);
+    }
+
+    #[test]
+    fn test_octant_hooks() {
+        use std::cell::RefCell;
+
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[17, 15]].opaque = true;
+        let events = RefCell::new(Vec::new());
+        fov::compute_with_octant_hooks(
+            [15, 15],
+            6,
+            &mut map,
+            |o| events.borrow_mut().push((o, true)),
+            |o| events.borrow_mut().push((o, false)),
+        );
+
+        let expected: Vec<_> = (0..8).flat_map(|o| [(o, true), (o, false)]).collect();
+        assert_eq!(events.into_inner(), expected);
+
+        let mut plain = VisibilityMap2d::default([30, 30]);
+        plain[[17, 15]].opaque = true;
+        fov::compute([15, 15], 6, &mut plain);
+        assert!(map
+            .iter()
+            .map(|p| p.visible)
+            .eq(plain.iter().map(|p| p.visible)));
+
+        /// Logs when the origin is marked visible.
+        struct OriginLog<'a> {
+            origin: IVec2,
+            events: &'a RefCell<Vec<String>>,
+        }
+
+        impl<'a> VisibilityMap for OriginLog<'a> {
+            fn is_opaque(&self, _: impl GridPoint) -> bool {
+                false
+            }
+
+            fn is_in_bounds(&self, p: impl GridPoint) -> bool {
+                let p = p.as_ivec2();
+                p.x >= 0 && p.y >= 0 && p.x < 30 && p.y < 30
+            }
+
+            fn set_visible(&mut self, p: impl GridPoint) {
+                if p.as_ivec2() == self.origin {
+                    self.events.borrow_mut().push("origin".to_string());
+                }
+            }
+
+            fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+                RangeMetric::Euclidean.dist(a, b)
+            }
+        }
+
+        let events = RefCell::new(Vec::new());
+        let mut map = OriginLog {
+            origin: IVec2::new(15, 15),
+            events: &events,
+        };
+        fov::compute_with_octant_hooks(
+            [15, 15],
+            6,
+            &mut map,
+            |o| events.borrow_mut().push(format!("start{}", o)),
+            |o| events.borrow_mut().push(format!("end{}", o)),
+        );
+        let events = events.into_inner();
+        assert_eq!(events[..3], ["origin", "start0", "end0"]);
+        assert_eq!(events.iter().filter(|e| *e == "origin").count(), 1);
+    }
+
+    #[test]
+    fn test_cone() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[14, 17]].opaque = true;
+        fov::compute_cone([15, 15], 5, [1, 0], 45.0, &mut map);
+
+        assert!(map[[15, 15]].visible);
+        assert!(map[[18, 15]].visible);
+        assert!(map[[18, 18]].visible);
+        assert!(map[[18, 12]].visible);
+        assert!(!map[[15, 18]].visible);
+        assert!(!map[[12, 15]].visible);
+        assert!(!map[[14, 17]].visible);
+
+        let mut map = VisibilityMap2d::default([30, 30]);
+        fov::compute_cone([15, 15], 5, [0, 1], 100.0, &mut map);
+        assert!(map[[15, 20]].visible);
+        assert!(map[[19, 15]].visible);
+        assert!(!map[[19, 13]].visible);
+        assert!(!map[[15, 12]].visible);
+    }
+
+    #[test]
+    fn test_cone_angle() {
+        let visible = |facing: f32, half_angle: f32| {
+            let mut map = VisibilityMap2d::default([30, 30]);
+            fov::compute_cone_angle([15, 15], 8, facing, half_angle, &mut map);
+            map
+        };
+
+        let east = visible(0.0, 30.0);
+        assert!(east[[22, 15]].visible);
+        assert!(!east[[15, 22]].visible);
+        assert!(east
+            .iter()
+            .map(|p| p.visible)
+            .eq(visible(360.0, 30.0).iter().map(|p| p.visible)));
+
+        // Wraps around from just below 360 to just above 0
+        let wrapped = visible(350.0, 20.0);
+        assert!(wrapped[[22, 16]].visible);
+        assert!(wrapped[[22, 13]].visible);
+        assert!(!wrapped[[18, 20]].visible);
+
+        // Wider than a half circle
+        let wide = visible(90.0, 135.0);
+        assert!(wide[[22, 15]].visible);
+        assert!(wide[[8, 15]].visible);
+        assert!(wide[[15, 22]].visible);
+        assert!(!wide[[15, 8]].visible);
+        assert!(wide[[19, 11]].visible);
+    }
+
+    #[test]
+    fn test_cone_zero_facing() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[17, 15]].opaque = true;
+        let mut expected = VisibilityMap2d::default([30, 30]);
+        expected[[17, 15]].opaque = true;
+        fov::compute([15, 15], 6, &mut expected);
+
+        fov::compute_cone([15, 15], 6, [0, 0], 30.0, &mut map);
+        assert!(map
+            .iter()
+            .zip(expected.iter())
+            .all(|(a, b)| a.visible == b.visible));
+
+        map.clear_visible();
+        fov::FovConfig::new(6)
+            .cone([0, 0], 30.0)
+            .compute([15, 15], &mut map);
+        assert!(map
+            .iter()
+            .zip(expected.iter())
+            .all(|(a, b)| a.visible == b.visible));
+    }
+
+    #[test]
+    fn test_directional() {
+        let map = VisibilityMap2d::default([30, 30]);
+        // A one-way window along y = 17 that can only be seen through from below
+        let blocks = |from: IVec2, to: IVec2| to.y == 17 && from.y > to.y;
+
+        let mut from_below = Vec::new();
+        fov::compute_directional([15, 15], 6, &map, blocks, |p| from_below.push(p));
+        assert!(from_below.contains(&IVec2::new(15, 17)));
+        assert!(from_below.contains(&IVec2::new(15, 20)));
+
+        let mut from_above = Vec::new();
+        fov::compute_directional([15, 20], 6, &map, blocks, |p| from_above.push(p));
+        assert!(from_above.contains(&IVec2::new(15, 17)));
+        assert!(!from_above.contains(&IVec2::new(15, 16)));
+        assert!(!from_above.contains(&IVec2::new(15, 15)));
+    }
+
+    #[test]
+    fn test_angles() {
+        use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+        let map = VisibilityMap2d::default([30, 30]);
+        let mut angles = Vec::new();
+        fov::compute_angles([10, 10], 5, &map, |p, a| angles.push((p, a)));
+        let angle = |p: [i32; 2]| angles.iter().find(|(t, _)| *t == IVec2::from(p)).unwrap().1;
+
+        assert_eq!(angle([10, 10]), 0.0);
+        assert_eq!(angle([14, 10]), 0.0);
+        assert_eq!(angle([10, 14]), FRAC_PI_2);
+        assert_eq!(angle([6, 10]), PI);
+        assert_eq!(angle([10, 6]), -FRAC_PI_2);
+        assert!((angle([12, 12]) - FRAC_PI_4).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_local() {
+        let mut local = VisibilityMap2d::default([30, 30]);
+        local[[17, 15]].opaque = true;
+        let offset = IVec2::new(1_000_000, -2_000_000);
+        fov::compute_local(IVec2::new(15, 15) + offset, offset, 6, &mut local);
+
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[17, 15]].opaque = true;
+        fov::compute([15, 15], 6, &mut map);
+        assert!(local
+            .iter()
+            .map(|p| p.visible)
+            .eq(map.iter().map(|p| p.visible)));
+    }
+
+    #[test]
+    fn test_debug_local() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[17, 15]].opaque = true;
+        let mut tiles = Vec::new();
+        fov::compute_debug_local([15, 15], 6, &map, |p, local, octant| {
+            tiles.push((p, local, octant))
+        });
+
+        assert!(tiles.contains(&(IVec2::new(15, 15), IVec2::ZERO, -1)));
+        // East-northeast runs along +x towards +y
+        assert!(tiles.contains(&(IVec2::new(19, 17), IVec2::new(4, 2), 7)));
+        // North-northwest runs along +y towards -x
+        assert!(tiles.contains(&(IVec2::new(14, 20), IVec2::new(5, 1), 5)));
+        assert!(tiles
+            .iter()
+            .all(|(_, local, _)| local.y >= 0 && local.y <= local.x));
+    }
+
+    #[test]
+    fn test_symmetry() {
+        // A diagonal corridor running from the origin
+        let mut map = VisibilityMap2d::new(
+            VisibilityPoint {
+                opaque: true,
+                visible: false,
+            },
+            [20, 20],
+        );
+        for i in 2..18 {
+            map[[i, i]].opaque = false;
+            map[[i + 1, i]].opaque = false;
+        }
+
+        fov::compute_with_symmetry([2, 2], 20, Symmetry::Balanced, &mut map);
+        let balanced = map.iter().filter(|p| p.visible).count();
+
+        map.clear_visible();
+        fov::compute_with_symmetry([2, 2], 20, Symmetry::Full, &mut map);
+        let full = map.iter().filter(|p| p.visible).count();
+
+        assert!(full < balanced);
+        // Every visible floor tile can see the origin
+        for x in 0..20 {
+            for y in 0..20 {
+                if map[[x, y]].visible && !map[[x, y]].opaque {
+                    assert!(fov::line_of_sight([x, y], [2, 2], &map));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_walls() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        for y in 10..=20 {
+            map[[18, y]].opaque = true;
+        }
+        let mut floor = Vec::new();
+        let mut walls = Vec::new();
+        fov::compute_walls([15, 15], 5, &map, |p| floor.push(p), |p| walls.push(p));
+
+        assert!(walls.contains(&IVec2::new(18, 15)));
+        assert!(!walls.contains(&IVec2::new(19, 15)));
+        assert!(floor.contains(&IVec2::new(15, 15)));
+        assert!(floor.contains(&IVec2::new(17, 15)));
+        assert!(!floor.contains(&IVec2::new(18, 15)));
+
+        let mut all = floor.clone();
+        all.extend(&walls);
+        let len = all.len();
+        all.sort_by_key(|p| (p.y, p.x));
+        all.dedup();
+        assert_eq!(len, all.len());
+    }
+
+    #[test]
+    fn test_with_walls() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[17, 15]].opaque = true;
+        map[[18, 15]].opaque = true;
+
+        let mut walls = Vec::new();
+        let mut floors = Vec::new();
+        fov::compute_with_walls([15, 15], 5, &map, |p, is_wall| {
+            if is_wall {
+                walls.push(p);
+            } else {
+                floors.push(p);
+            }
+        });
+
+        assert!(walls.contains(&IVec2::new(17, 15)));
+        assert!(!walls.contains(&IVec2::new(18, 15)));
+        assert!(floors.contains(&IVec2::new(16, 15)));
+        assert!(!floors.contains(&IVec2::new(19, 15)));
+        assert!(walls.iter().all(|p| map[*p].opaque));
+        assert!(floors.iter().all(|p| !map[*p].opaque));
+    }
+
+    #[test]
+    fn test_corridor_walls() {
+        // A horizontal corridor with a side opening
+        let mut map = VisibilityMap2d::new(
+            VisibilityPoint {
+                opaque: true,
+                visible: false,
+            },
+            [20, 5],
+        );
+        for x in 1..19 {
+            map[[x, 2]].opaque = false;
+        }
+        map[[6, 3]].opaque = false;
+
+        let mut balanced = map.clone();
+        fov::compute_with_symmetry([2, 2], 20, Symmetry::Balanced, &mut balanced);
+        let mut full = map.clone();
+        fov::compute_with_symmetry([2, 2], 20, Symmetry::Full, &mut full);
+
+        for i in 0..map.len() {
+            if map[i].opaque {
+                // Walls are never more visible in full mode
+                assert!(!full[i].visible || balanced[i].visible);
+            } else {
+                assert_eq!(full[i].visible, balanced[i].visible);
+            }
+        }
+        // The far walls of the corridor are only lit in balanced mode
+        assert!(balanced[[15, 3]].visible);
+        assert!(!full[[15, 3]].visible);
+        // The side opening is hidden from both
+        assert!(!balanced[[6, 4]].visible);
+    }
+
+    #[test]
+    fn test_corner_peek() {
+        let mut map = VisibilityMap2d::default([20, 20]);
+        // A corridor heading north that turns east
+        for y in 2..=7 {
+            map[[4, y]].opaque = true;
+        }
+        for y in 2..=5 {
+            map[[6, y]].opaque = true;
+        }
+        for x in 5..=9 {
+            map[[x, 7]].opaque = true;
+            map[[x + 2, 5]].opaque = true;
+        }
+
+        let mut tiles = Vec::new();
+        fov::FovConfig::new(5).compute_with([5, 4], &map, |p| tiles.push(p));
+        assert!(tiles.contains(&IVec2::new(6, 6)));
+        assert!(!tiles.contains(&IVec2::new(7, 6)));
+
+        tiles.clear();
+        fov::FovConfig::new(5)
+            .corner_peek(true)
+            .compute_with([5, 4], &map, |p| tiles.push(p));
+        assert!(tiles.contains(&IVec2::new(7, 6)));
+        assert!(!tiles.contains(&IVec2::new(8, 6)));
+
+        // A straight wall has no corner to peek around
+        tiles.clear();
+        fov::FovConfig::new(5)
+            .corner_peek(true)
+            .compute_with([5, 3], &map, |p| tiles.push(p));
+        assert!(!tiles.contains(&IVec2::new(7, 5)));
+        assert!(!tiles.contains(&IVec2::new(3, 5)));
+    }
+
+    #[test]
+    fn test_diagonal_rule() {
+        let mut map = VisibilityMap2d::default([20, 20]);
+        for i in 0..8 {
+            map[[4 + i, 12 - i]].opaque = true;
+        }
+
+        fov::compute_with_diagonal_rule([5, 5], 12, DiagonalRule::Permissive, &mut map);
+        assert!(map[[9, 10]].visible);
+
+        map.clear_visible();
+        fov::compute_with_diagonal_rule([5, 5], 12, DiagonalRule::Blocking, &mut map);
+        assert!(map[[6, 10]].visible);
+        assert!(!map[[9, 10]].visible);
+        assert!(!map[[13, 13]].visible);
+    }
+
+    #[test]
+    fn test_overscan() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[10, 15]].opaque = true;
+        map[[10, 16]].opaque = true;
+        map[[16, 10]].opaque = true;
+
+        fov::compute([10, 10], 5, &mut map);
+        assert!(map[[10, 15]].visible);
+        assert!(!map[[10, 16]].visible);
+        assert!(!map[[16, 10]].visible);
+
+        map.clear_visible();
+        fov::compute_overscan([10, 10], 5, &mut map);
+        assert!(map[[10, 15]].visible);
+        assert!(map[[16, 10]].visible);
+        // Only reachable through another wall
+        assert!(!map[[10, 16]].visible);
+        // Floors stay within range
+        assert!(!map[[4, 10]].visible);
+    }
+
+    #[test]
+    fn test_shadows() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[12, 10]].opaque = true;
+
+        let mut visible = Vec::new();
+        let mut shadowed = Vec::new();
+        fov::compute_with_shadows([10, 10], 5, &map, |p| visible.push(p), |p| shadowed.push(p));
+        assert!(visible.contains(&IVec2::new(12, 10)));
+        assert!(shadowed.contains(&IVec2::new(13, 10)));
+        assert!(shadowed.contains(&IVec2::new(15, 10)));
+        assert!(!shadowed.contains(&IVec2::new(16, 10)));
+        assert!(!shadowed.iter().any(|p| visible.contains(p)));
+
+        let disc = (0..30 * 30)
+            .map(|i| map.index_to_pos(i))
+            .filter(|p| map.dist([10, 10], *p) <= 5.0)
+            .count();
+        assert_eq!(visible.len() + shadowed.len(), disc);
+    }
+
+    /// An unbounded map with a repeating pattern of walls.
+    struct Pillars;
+
+    impl VisibilityMap for Pillars {
+        fn is_opaque(&self, p: impl GridPoint) -> bool {
+            p.x().rem_euclid(5) == 0 && p.y().rem_euclid(3) == 0
+        }
+
+        fn is_in_bounds(&self, _: impl GridPoint) -> bool {
+            true
+        }
+
+        fn set_visible(&mut self, _: impl GridPoint) {}
+
+        fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+            RangeMetric::Euclidean.dist(a, b)
+        }
+    }
+
+    #[test]
+    fn test_large_coordinates() {
+        let offset = IVec2::splat(30000);
+        let near = fov::compute_tiles([16, 17], 20, &Pillars);
+        let far = fov::compute_tiles(IVec2::new(16, 17) + offset, 20, &Pillars);
+
+        let far: Vec<_> = far.into_iter().map(|p| p - offset).collect();
+        assert_eq!(near, far);
+
+        // Right up against the edge of i32, keeping the pillars lined up
+        let offset = IVec2::splat((i32::MAX - 100) / 15 * 15);
+        let edge = fov::compute_tiles(IVec2::new(16, 17) + offset, 20, &Pillars);
+        let edge: Vec<_> = edge.into_iter().map(|p| p - offset).collect();
+        assert_eq!(near, edge);
+    }
+
+    #[test]
+    fn test_slope_large_coordinates() {
+        use internal::Slope;
+
+        // Every product here is past i32::MAX
+        let slope = Slope::new(60000, 70000);
+        assert!(slope.less(50001, 58334));
+        assert!(!slope.greater_or_equal(50001, 58334));
+        assert!(slope.greater(50000, 58334));
+        assert!(slope.less_or_equal(60000, 70000));
+        assert!(slope.greater_or_equal(60000, 70000));
+        assert!(!slope.greater(60000, 70000));
+
+        assert_eq!(slope.column_y(80000), 68571);
+        assert_eq!(Slope::new(50000, 50000).column_y(90000), 90000);
+        assert_eq!(Slope::new(99999, 100001).column_y(100001), 99999);
+    }
+
+    #[test]
+    fn test_line_of_sight() {
+        let mut map = VisibilityMap2d::default([20, 20]);
+        for i in 0..20 {
+            map[[(i * 7) % 20, (i * 13) % 20]].opaque = true;
+        }
+        map[[10, 10]].opaque = false;
+
+        fov::compute([10, 10], 100, &mut map);
+        for x in 0..20 {
+            for y in 0..20 {
+                assert_eq!(
+                    map[[x, y]].visible,
+                    fov::line_of_sight([10, 10], [x, y], &map)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_line_of_sight_symmetry() {
+        // Simple xorshift so the layouts are random but repeatable
+        let mut seed = 0x2545_f491_u32;
+        let mut rand = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed
+        };
+
+        for _ in 0..10 {
+            let mut map = VisibilityMap2d::default([10, 10]);
+            let density = rand() % 40;
+            map.iter_mut()
+                .for_each(|p| p.opaque = rand() % 100 < density);
+
+            for a in (0..100).map(|i| map.index_to_pos(i)) {
+                for b in (0..100).map(|i| map.index_to_pos(i)) {
+                    assert_eq!(
+                        fov::line_of_sight(a, b, &map),
+                        fov::line_of_sight(b, a, &map),
+                        "{:?} -> {:?}",
+                        a,
+                        b
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_line_of_sight_opaque_symmetry() {
+        // The floor tile at a sees the wall at b, but b is an opaque origin and
+        // with the default OpaqueOrigin::OnlySelf it only sees itself
+        let mut map = VisibilityMap2d::default([6, 6]);
+        map[[2, 4]].opaque = true;
+        map[[5, 4]].opaque = true;
+        map[[5, 3]].opaque = true;
+        let (a, b) = ([4, 0], [5, 4]);
+
+        assert!(fov::is_visible_from(a, b, 10, &map));
+        assert!(!fov::is_visible_from(b, a, 10, &map));
+        assert!(fov::line_of_sight(a, b, &map));
+        assert!(fov::line_of_sight(b, a, &map));
+    }
+
+    #[test]
+    fn test_is_visible_from() {
+        let mut map = VisibilityMap2d::default([20, 20]);
+        map[[12, 10]].opaque = true;
+        map[[8, 13]].opaque = true;
+
+        fov::compute([10, 10], 6, &mut map);
+        for i in 0..map.len() {
+            let p = map.index_to_pos(i);
+            assert_eq!(fov::is_visible_from([10, 10], p, 6, &map), map[p].visible);
+        }
+        assert!(!fov::is_visible_from([10, 10], [25, 10], 30, &map));
+    }
+
+    #[test]
+    fn test_cast_ray() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[20, 17]].opaque = true;
+        map[[25, 15]].opaque = true;
+
+        assert_eq!(None, fov::cast_ray([15, 15], [24, 15], &map));
+        assert_eq!(
+            Some(IVec2::new(25, 15)),
+            fov::cast_ray([15, 15], [28, 15], &map)
+        );
+        assert_eq!(
+            Some(IVec2::new(20, 17)),
+            fov::cast_ray([15, 15], [25, 19], &map)
+        );
+        assert_eq!(
+            Some(IVec2::new(30, 15)),
+            fov::cast_ray([26, 15], [35, 15], &map)
+        );
+        assert_eq!(None, fov::cast_ray([15, 15], [15, 15], &map));
+    }
+
+    #[test]
+    fn test_tiles() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[16, 15]].opaque = true;
+        let tiles = fov::compute_tiles([15, 15], 5, &map);
+
+        assert_eq!(
+            1,
+            tiles.iter().filter(|p| **p == IVec2::new(15, 15)).count()
+        );
+        assert!(tiles.contains(&IVec2::new(16, 15)));
+        assert!(!tiles.contains(&IVec2::new(17, 15)));
+
+        fov::compute([15, 15], 5, &mut map);
+        let visible = map.iter().filter(|p| p.visible).count();
+        assert_eq!(visible, tiles.len());
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[16, 15]].opaque = true;
+        map[[13, 14]].opaque = true;
+
+        let mut tiles: Vec<_> = fov::iter([15, 15], 7, &map).collect();
+        tiles.sort_unstable_by_key(|p| (p.y, p.x));
+        assert_eq!(fov::compute_tiles([15, 15], 7, &map), tiles);
+    }
+
+    #[test]
+    fn test_iter_with_dist() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[17, 15]].opaque = true;
+
+        let tiles: Vec<_> = fov::iter_with_dist([15, 15], 6, &map).collect();
+        let mut positions: Vec<_> = tiles.iter().map(|(p, _)| *p).collect();
+        let mut expected = fov::compute_tiles([15, 15], 6, &map);
+        positions.sort_by_key(|p| (p.y, p.x));
+        expected.sort_by_key(|p| (p.y, p.x));
+        assert_eq!(positions, expected);
+
+        for (p, dist) in tiles {
+            assert_eq!(dist, map.dist([15, 15], p));
+        }
+    }
+
+    #[test]
+    fn test_unique() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[17, 16]].opaque = true;
+
+        let mut marks = Grid::new(0, [30, 30]);
+        fov::compute_unique([15, 15], 8, &map, |p| marks[p] += 1);
+
+        assert!(marks.iter().all(|count| *count <= 1));
+        let visible = marks.iter().filter(|count| **count == 1).count();
+        assert_eq!(fov::compute_tiles([15, 15], 8, &map).len(), visible);
+    }
+
+    #[test]
+    fn test_into_grid() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[16, 15]].opaque = true;
+
+        let mut visible = Grid::new(true, [20, 20]);
+        fov::compute_into_grid([15, 15], 5, &map, &mut visible);
+
+        assert!(visible[[15, 15]]);
+        assert!(visible[[16, 15]]);
+        assert!(!visible[[17, 15]]);
+        assert!(!visible[[0, 0]]);
+        assert!(visible[[15, 19]]);
+    }
+
+    #[test]
+    fn test_into_vec() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[17, 15]].opaque = true;
+        let mut tiles = vec![IVec2::new(-5, -5)];
+
+        fov::compute_into_vec([15, 15], 6, &map, &mut tiles);
+        assert_eq!(tiles, fov::compute_tiles([15, 15], 6, &map));
+
+        let capacity = tiles.capacity();
+        fov::compute_into_vec([10, 10], 3, &map, &mut tiles);
+        assert_eq!(tiles, fov::compute_tiles([10, 10], 3, &map));
+        assert_eq!(tiles.capacity(), capacity);
+    }
+
+    #[test]
+    fn test_sorted() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[12, 10]].opaque = true;
+
+        let tiles = fov::compute_sorted([10, 10], 5, &map);
+        assert_eq!(tiles[0], IVec2::new(10, 10));
+        assert_eq!(
+            &tiles[1..5],
+            &[[10, 9], [9, 10], [11, 10], [10, 11]].map(IVec2::from)
+        );
+        let dist = |p: IVec2| (p - IVec2::new(10, 10)).as_vec2().length();
+        assert!(tiles.windows(2).all(|w| dist(w[0]) <= dist(w[1])));
+        assert_eq!(tiles.len(), fov::compute_tiles([10, 10], 5, &map).len());
+    }
+
+    /// A grid map measuring distance with [RangeMetric::Manhattan].
+    struct Diamond(VisibilityMap2d);
+
+    impl VisibilityMap for Diamond {
+        fn is_opaque(&self, p: impl GridPoint) -> bool {
+            self.0.is_opaque(p)
+        }
+
+        fn is_in_bounds(&self, p: impl GridPoint) -> bool {
+            self.0.is_in_bounds(p)
+        }
+
+        fn set_visible(&mut self, _: impl GridPoint) {}
+
+        fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+            RangeMetric::Manhattan.dist(a, b)
+        }
+    }
+
+    #[test]
+    fn test_sorted_by_map_dist() {
+        let mut map = Diamond(VisibilityMap2d::default([30, 30]));
+        map.0[[12, 10]].opaque = true;
+
+        let tiles = fov::compute_sorted([10, 10], 6, &map);
+        let mut radial = Vec::new();
+        fov::compute_radial([10, 10], 6, &map, |p, _| radial.push(p));
+        assert_eq!(tiles, radial);
+
+        let dist = |p: IVec2| map.dist([10, 10], p);
+        assert!(tiles.windows(2).all(|w| dist(w[0]) <= dist(w[1])));
+        // Tied by manhattan distance, so sorted by y, although [12, 11] is
+        // nearer by euclidean distance
+        let a = tiles.iter().position(|p| *p == IVec2::new(10, 7)).unwrap();
+        let b = tiles.iter().position(|p| *p == IVec2::new(12, 11)).unwrap();
+        assert!(a < b);
+    }
+
+    #[test]
+    fn test_radial() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[17, 15]].opaque = true;
+        let mut tiles = Vec::new();
+        fov::compute_radial([15, 15], 6, &map, |p, dist| tiles.push((p, dist)));
+
+        assert_eq!(tiles[0], (IVec2::new(15, 15), 0.0));
+        assert!(tiles.windows(2).all(|w| w[0].1 <= w[1].1));
+        assert!(tiles.iter().all(|(p, d)| *d == map.dist([15, 15], *p)));
+
+        let mut positions: Vec<_> = tiles.iter().map(|(p, _)| *p).collect();
+        positions.sort_unstable_by_key(|p| (p.y, p.x));
+        assert_eq!(positions, fov::compute_tiles([15, 15], 6, &map));
+    }
+
+    #[test]
+    fn test_visible_tile_count() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        assert_eq!(fov::visible_tile_count([10, 10], 1, &map), 5);
+
+        map[[11, 10]].opaque = true;
+        let count = fov::visible_tile_count([10, 10], 5, &map);
+        assert_eq!(count, fov::compute_tiles([10, 10], 5, &map).len());
+        assert!(count < fov::visible_tile_count([20, 20], 5, &map));
+    }
+
+    #[test]
+    fn test_area() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        for x in 10..=20 {
+            map[[x, 15]].opaque = true;
+        }
+        // A window in the wall
+        let window = [IVec2::new(14, 15), IVec2::new(15, 15), IVec2::new(16, 15)];
+        for p in window {
+            map[p].opaque = false;
+        }
+
+        let mut tiles = Vec::new();
+        fov::compute_area(&window, 4, &map, |p| tiles.push(p));
+
+        assert!(tiles.contains(&IVec2::new(12, 17)));
+        assert!(tiles.contains(&IVec2::new(18, 13)));
+        assert!(!tiles.contains(&IVec2::new(20, 15)));
+        let len = tiles.len();
+        tiles.sort_unstable_by_key(|p| (p.y, p.x));
+        tiles.dedup();
+        assert_eq!(len, tiles.len());
+    }
+
+    #[test]
+    fn test_perimeter() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[17, 15]].opaque = true;
+        let perimeter = fov::compute_perimeter([15, 15], 5, &map);
+
+        assert!(perimeter.contains(&IVec2::new(15, 20)));
+        assert!(perimeter.contains(&IVec2::new(18, 19)));
+        assert!(!perimeter.contains(&IVec2::new(15, 19)));
+        // Hidden behind the wall
+        assert!(!perimeter.contains(&IVec2::new(20, 15)));
+        assert!(perimeter
+            .iter()
+            .all(|p| map.dist([15, 15], *p).round() as i32 == 5));
+    }
+
+    #[test]
+    fn test_frontier() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        assert_eq!(
+            fov::compute_frontier([10, 10], 1, &map),
+            [[10, 9], [9, 10], [11, 10], [10, 11]].map(IVec2::from)
+        );
+
+        map[[12, 10]].opaque = true;
+        let frontier = fov::compute_frontier([10, 10], 5, &map);
+        // The range edge and the shadow edge
+        assert!(frontier.contains(&IVec2::new(10, 15)));
+        assert!(frontier.contains(&IVec2::new(12, 10)));
+        assert!(frontier.contains(&IVec2::new(14, 11)));
+        assert!(!frontier.contains(&IVec2::new(10, 10)));
+        assert!(!frontier.contains(&IVec2::new(11, 12)));
+    }
+
+    #[test]
+    fn test_fov_result() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[17, 15]].opaque = true;
+        let result = FovResult::new([15, 15], 6, [30, 30], &map);
+
+        assert!(result.is_visible([15, 15]));
+        assert!(result.is_visible([17, 15]));
+        assert!(!result.is_visible([18, 15]));
+        assert!(!result.is_visible([-1, 15]));
+        assert!(!result.is_visible([15, 30]));
+
+        let tiles: Vec<_> = result.visible_tiles().collect();
+        assert_eq!(tiles, fov::compute_tiles([15, 15], 6, &map));
+        assert_eq!(result.len(), tiles.len());
+
+        // Tiles outside the region are dropped
+        let clipped = FovResult::new([15, 15], 6, [16, 16], &map);
+        assert!(clipped.visible_tiles().all(|p| p.x < 16 && p.y < 16));
+        assert!(!clipped.is_empty());
+    }
+
+    #[test]
+    fn test_visibility_table() {
+        let mut map = VisibilityMap2d::default([12, 12]);
+        map[[5, 5]].opaque = true;
+        map[[6, 2]].opaque = true;
+
+        let table = VisibilityTable::new([12, 12], 6, &map);
+        for a in (0..144).map(|i| map.index_to_pos(i)) {
+            let tiles = fov::compute_tiles(a, 6, &map);
+            for b in (0..144).map(|i| map.index_to_pos(i)) {
+                assert_eq!(table.is_visible(a, b), tiles.contains(&b));
+            }
+        }
+        assert!(!table.is_visible([-1, 0], [0, 0]));
+        assert!(!table.is_visible([0, 0], [12, 0]));
+    }
+
+    #[test]
+    fn test_debug() {
+        let map = VisibilityMap2d::default([30, 30]);
+        let mut tiles = Vec::new();
+        fov::compute_debug([15, 15], 5, &map, |p, octant| tiles.push((p, octant)));
+
+        assert!(tiles.contains(&(IVec2::new(15, 15), -1)));
+        assert!(tiles.contains(&(IVec2::new(18, 14), 0)));
+        assert!(tiles.contains(&(IVec2::new(18, 16), 7)));
+        assert!(tiles.iter().all(|(_, octant)| (-1..8).contains(octant)));
+    }
+
+    #[cfg(feature = "debug")]
+    #[test]
+    fn test_to_string() {
+        let walls = [IVec2::new(3, 2), IVec2::new(3, 1), IVec2::new(3, 3)];
+        let string = fov::to_string(IVec2::new(1, 2), 3, 6, 5, |p| walls.contains(&p));
+
+        assert_eq!(
+            string,
+            ["....  \n", "...#  \n", ".@.#  \n", "...#  \n", "....  \n",].concat()
+        );
+    }
+
+    #[test]
+    fn test_until() {
+        use std::ops::ControlFlow;
+
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[18, 18]].opaque = true;
+
+        let mut count = 0;
+        let result = fov::compute_until([15, 15], 10, &map, |p| {
+            count += 1;
+            if map[p].opaque {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        assert_eq!(ControlFlow::Break(()), result);
+        assert!(count < fov::compute_tiles([15, 15], 10, &map).len());
+
+        let result = fov::compute_until([15, 15], 2, &map, |p| {
+            if map[p].opaque {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        assert_eq!(ControlFlow::Continue(()), result);
+    }
+
+    #[test]
+    fn test_cancellable() {
+        use std::{
+            ops::ControlFlow,
+            sync::atomic::{AtomicBool, Ordering},
+        };
+
+        let mut map = VisibilityMap2d::default([30, 30]);
+        let cancel = AtomicBool::new(false);
+        let flow =
+            fov::compute_cancellable([15, 15], 6, &mut map, || cancel.load(Ordering::Relaxed));
+        assert_eq!(flow, ControlFlow::Continue(()));
+        assert_eq!(
+            map.iter().filter(|p| p.visible).count(),
+            fov::visible_tile_count([15, 15], 6, &map)
+        );
+
+        map.clear_visible();
+        let mut polls = 0;
+        let flow = fov::compute_cancellable([15, 15], 6, &mut map, || {
+            polls += 1;
+            polls > 10
+        });
+        assert_eq!(flow, ControlFlow::Break(()));
+        assert!(map[[15, 15]].visible);
+        assert!(map.iter().filter(|p| p.visible).count() <= 10);
+    }
+
+    #[test]
+    fn test_budgeted() {
+        use std::ops::ControlFlow;
+
+        let mut map = VisibilityMap2d::default([30, 30]);
+        let result = fov::compute_budgeted([15, 15], 10, 20, &mut map);
+        assert_eq!(ControlFlow::Break(()), result);
+        assert_eq!(20, map.iter().filter(|p| p.visible).count());
+
+        let mut map = VisibilityMap2d::default([30, 30]);
+        let result = fov::compute_budgeted([15, 15], 1, 20, &mut map);
+        assert_eq!(ControlFlow::Continue(()), result);
+        assert_eq!(5, map.iter().filter(|p| p.visible).count());
+
+        // A budget of exactly the fov's size isn't exceeded
+        let mut map = VisibilityMap2d::default([30, 30]);
+        let result = fov::compute_budgeted([15, 15], 1, 5, &mut map);
+        assert_eq!(ControlFlow::Continue(()), result);
+        assert_eq!(5, map.iter().filter(|p| p.visible).count());
+
+        let mut map = VisibilityMap2d::default([30, 30]);
+        let result = fov::compute_budgeted([15, 15], 1, 4, &mut map);
+        assert_eq!(ControlFlow::Break(()), result);
+        assert_eq!(4, map.iter().filter(|p| p.visible).count());
+
+        let mut map = VisibilityMap2d::default([30, 30]);
+        let result = fov::compute_budgeted([15, 15], 1, 0, &mut map);
+        assert_eq!(ControlFlow::Break(()), result);
+        assert_eq!(0, map.iter().filter(|p| p.visible).count());
+
+        // Nothing is visible from outside the map, so no budget is needed
+        let mut map = VisibilityMap2d::default([30, 30]);
+        let result = fov::compute_budgeted([-5, -5], 1, 0, &mut map);
+        assert_eq!(ControlFlow::Continue(()), result);
+    }
+
+    #[test]
+    fn test_remaining() {
+        let map = VisibilityMap2d::default([30, 30]);
+        let mut remaining = std::collections::HashMap::new();
+        fov::compute_remaining([15, 15], 5, &map, |p, r| {
+            remaining.insert(p, r);
+        });
+
+        assert_eq!(remaining[&IVec2::new(15, 15)], 5);
+        assert_eq!(remaining[&IVec2::new(16, 15)], 4);
+        assert_eq!(remaining[&IVec2::new(16, 16)], 3);
+        assert_eq!(remaining[&IVec2::new(15, 20)], 0);
+        assert!(remaining.values().all(|r| (0..=5).contains(r)));
+    }
+
+    #[test]
+    fn test_attenuated() {
+        let map = VisibilityMap2d::default([30, 30]);
+        let mut tiles = Vec::new();
+        fov::compute_attenuated([15, 15], 5, &map, |p, d| tiles.push((p, d)));
+
+        assert!(tiles.contains(&(IVec2::new(15, 15), 0.0)));
+        assert!(tiles.contains(&(IVec2::new(20, 15), 5.0)));
+        assert!(tiles.contains(&(IVec2::new(18, 11), 5.0)));
+        assert!(tiles.iter().all(|(_, d)| *d <= 5.0));
+    }
+
+    #[test]
+    fn test_falloff_negative_range() {
+        for falloff in [Falloff::Linear, Falloff::Smoothstep] {
+            assert_eq!(falloff.brightness(0.0, -1.0), 1.0);
+            assert_eq!(falloff.brightness(1.0, -1.0), 0.0);
+            assert_eq!(falloff.brightness(1.0, -5.0), 0.0);
+        }
+        assert_eq!(Falloff::InverseSquare.brightness(1.0, -1.0), 0.5);
+        assert_eq!(Falloff::InverseSquare.brightness(1.0, 10.0), 0.5);
+    }
+
+    #[test]
+    fn test_lit() {
+        let map = VisibilityMap2d::default([30, 30]);
+        for falloff in [Falloff::Linear, Falloff::InverseSquare, Falloff::Smoothstep] {
+            let mut lit = Grid::new(-1.0, [30, 30]);
+            fov::compute_lit([15, 15], 5, falloff, &map, |p, b| lit[p] = b);
+
+            assert_eq!(1.0, lit[[15, 15]]);
+            assert!(lit[[16, 15]] > lit[[18, 15]]);
+            assert!(lit[[20, 15]] > 0.0 && lit[[20, 15]] < 0.5);
+            assert_eq!(-1.0, lit[[21, 15]]);
+        }
+    }
+
+    #[test]
+    fn test_lit_with_metrics() {
+        let map = VisibilityMap2d::default([30, 30]);
+        let mut lit = std::collections::HashMap::new();
+        fov::compute_lit_with_metrics(
+            [15, 15],
+            4,
+            RangeMetric::Chebyshev,
+            RangeMetric::Manhattan,
+            Falloff::Linear,
+            &map,
+            |p, b| {
+                lit.insert(p, b);
+            },
+        );
+
+        // Square edge, diamond falloff
+        assert!(lit.contains_key(&IVec2::new(19, 19)));
+        assert!(!lit.contains_key(&IVec2::new(20, 15)));
+        assert_eq!(lit[&IVec2::new(15, 15)], 1.0);
+        assert_eq!(lit[&IVec2::new(16, 16)], lit[&IVec2::new(17, 15)]);
+        assert_eq!(lit[&IVec2::new(19, 19)], 0.0);
+    }
+
+    #[test]
+    fn test_vision_cost() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[10, 14]].opaque = true;
+
+        // With clear tiles everywhere it's the same as a normal fov
+        let mut tiles = Vec::new();
+        fov::compute_with_cost([10, 10], 6, &map, |_| 1.0, |p| tiles.push(p));
+        tiles.sort_unstable_by_key(|p| (p.y, p.x));
+        tiles.dedup();
+        assert_eq!(tiles, fov::compute_tiles([10, 10], 6, &map));
+
+        // Smoke to the east shortens vision through it
+        let smoke = |p: IVec2| if p.x == 12 { 3.0 } else { 1.0 };
+        let mut tiles = Vec::new();
+        fov::compute_with_cost([10, 10], 6, &map, smoke, |p| tiles.push(p));
+        assert!(tiles.contains(&IVec2::new(12, 10)));
+        assert!(tiles.contains(&IVec2::new(14, 10)));
+        assert!(!tiles.contains(&IVec2::new(15, 10)));
+        assert!(tiles.contains(&IVec2::new(4, 10)));
+
+        // Infinite cost blocks vision entirely
+        let wall = |p: IVec2| if p.x == 12 { f32::INFINITY } else { 1.0 };
+        let mut tiles = Vec::new();
+        fov::compute_with_cost([10, 10], 6, &map, wall, |p| tiles.push(p));
+        assert!(tiles.contains(&IVec2::new(12, 10)));
+        assert!(!tiles.contains(&IVec2::new(13, 10)));
+    }
+
+    #[test]
+    fn test_height() {
+        let map = VisibilityMap2d::default([30, 30]);
+        let cover = |p: IVec2| match p.to_array() {
+            [11, 10] | [14, 12] => 1,
+            [10, 12] => 3,
+            _ => 0,
+        };
+        let visible = |eye_height| {
+            let mut tiles = Vec::new();
+            fov::compute_with_height([10, 10], 6, eye_height, &map, cover, |p| tiles.push(p));
+            tiles
+        };
+
+        // Standing sees over nearby cover, but not cover near the target
+        let standing = visible(2);
+        assert!(standing.contains(&IVec2::new(11, 10)));
+        assert!(standing.contains(&IVec2::new(15, 10)));
+        assert!(!standing.contains(&IVec2::new(15, 12)));
+        // Too tall to see over at all
+        assert!(standing.contains(&IVec2::new(10, 12)));
+        assert!(!standing.contains(&IVec2::new(10, 14)));
+
+        // Crouching hides behind the nearby cover
+        let crouching = visible(1);
+        assert!(crouching.contains(&IVec2::new(11, 10)));
+        assert!(!crouching.contains(&IVec2::new(15, 10)));
+    }
+
+    #[test]
+    fn test_translucent() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[10, 13]].opaque = true;
+        let fog = |p: IVec2| if p.x > 11 { 0.3 } else { 0.0 };
+
+        let mut tiles = Vec::new();
+        fov::compute_translucent([10, 10], 8, &map, fog, |p| tiles.push(p));
+        // Three fog tiles can be seen through, but not four
+        assert!(tiles.contains(&IVec2::new(15, 10)));
+        assert!(!tiles.contains(&IVec2::new(16, 10)));
+        assert!(tiles.contains(&IVec2::new(10, 5)));
+        assert!(!tiles.contains(&IVec2::new(10, 14)));
+
+        // Full opacity is the same as opaque
+        let mut tiles = Vec::new();
+        let wall = |p: IVec2| if p.x == 12 { 1.0 } else { 0.0 };
+        fov::compute_translucent([10, 10], 8, &map, wall, |p| tiles.push(p));
+        for y in 0..30 {
+            map[[12, y]].opaque = true;
+        }
+        tiles.sort_unstable_by_key(|p| (p.y, p.x));
+        tiles.dedup();
+        assert_eq!(tiles, fov::compute_tiles([10, 10], 8, &map));
+    }
+
+    #[test]
+    fn test_wall_penetration() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        for y in 10..=20 {
+            map[[17, y]].opaque = true;
+            map[[19, y]].opaque = true;
+        }
+
+        let mut tiles = Vec::new();
+        fov::compute_with_penetration([15, 15], 8, 0, &map, |p| tiles.push(p));
+        tiles.sort_unstable_by_key(|p| (p.y, p.x));
+        assert_eq!(tiles, fov::compute_tiles([15, 15], 8, &map));
+
+        tiles.clear();
+        fov::compute_with_penetration([15, 15], 8, 1, &map, |p| tiles.push(p));
+        assert!(tiles.contains(&IVec2::new(18, 15)));
+        assert!(tiles.contains(&IVec2::new(19, 15)));
+        assert!(!tiles.contains(&IVec2::new(20, 15)));
+
+        tiles.clear();
+        fov::compute_with_penetration([15, 15], 8, 2, &map, |p| tiles.push(p));
+        assert!(tiles.contains(&IVec2::new(20, 15)));
+        assert!(!tiles.contains(&IVec2::new(24, 15)));
+        let len = tiles.len();
+        tiles.sort_unstable_by_key(|p| (p.y, p.x));
+        tiles.dedup();
+        assert_eq!(len, tiles.len());
+        // Only the map is searched, however large the range
+        let mut whole_map = Vec::new();
+        fov::compute_with_penetration([15, 15], 50, 2, &map, |p| whole_map.push(p));
+        let mut tiles = Vec::new();
+        fov::compute_with_penetration([15, 15], i32::MAX, 2, &map, |p| tiles.push(p));
+        assert_eq!(tiles, whole_map);
+    }
+
+    /// A grid map where the opaque tiles are holes outside of the map, which
+    /// panics if they're read.
+    struct Holes(VisibilityMap2d);
+
+    impl VisibilityMap for Holes {
+        fn is_opaque(&self, p: impl GridPoint) -> bool {
+            assert!(
+                self.is_in_bounds(p),
+                "read out of bounds at {:?}",
+                p.as_ivec2()
+            );
+            false
+        }
+
+        fn is_in_bounds(&self, p: impl GridPoint) -> bool {
+            self.0.in_bounds(p) && !self.0[p].opaque
+        }
+
+        fn set_visible(&mut self, _: impl GridPoint) {}
+
+        fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+            RangeMetric::Euclidean.dist(a, b)
+        }
+    }
+
+    #[test]
+    fn test_lines_stay_in_bounds() {
+        let mut seed = 0x1b87_3593_u32;
+        let mut rand = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed
+        };
+
+        for _ in 0..20 {
+            let mut grid = VisibilityMap2d::default([20, 20]);
+            grid.iter_mut().for_each(|p| p.opaque = rand() % 100 < 20);
+            let origin = IVec2::new((rand() % 20) as i32, (rand() % 20) as i32);
+            grid[origin].opaque = false;
+            let map = Holes(grid);
+
+            fov::compute_with_cost(origin, 15, &map, |_| 1.0, |_| {});
+            fov::compute_with_height(origin, 15, 2, &map, |_| 1, |_| {});
+            fov::compute_translucent(origin, 15, &map, |_| 0.0, |_| {});
+            fov::compute_with_penetration(origin, 15, 2, &map, |_| {});
+        }
+    }
+
+    #[test]
+    fn test_hex() {
+        // Indexed by axial coordinates
+        let mut map = VisibilityMap2d::default([20, 20]);
+        fov::compute_hex([10, 10], 3, &mut map);
+        assert_eq!(map.iter().filter(|p| p.visible).count(), 37);
+        assert!(map[[13, 10]].visible);
+        assert!(map[[13, 7]].visible);
+        assert!(map[[7, 13]].visible);
+        assert!(!map[[13, 13]].visible);
+
+        map.clear_visible();
+        map[[11, 10]].opaque = true;
+        fov::compute_hex([10, 10], 3, &mut map);
+        assert!(map[[11, 10]].visible);
+        assert!(!map[[12, 10]].visible);
+        assert!(!map[[13, 10]].visible);
+        assert!(map[[10, 13]].visible);
+    }
+
+    #[test]
+    fn test_wrapped() {
+        let mut map = VisibilityMap2d::default([20, 20]);
+        map[[18, 1]].opaque = true;
+        fov::compute_wrapped([1, 1], 4, [20, 20], &mut map);
+
+        assert!(map[[1, 1]].visible);
+        assert!(map[[19, 1]].visible);
+        assert!(map[[18, 1]].visible);
+        assert!(!map[[17, 1]].visible);
+        assert!(map[[1, 17]].visible);
+        assert!(map[[19, 19]].visible);
+        assert!(!map[[1, 16]].visible);
+    }
+
+    #[test]
+    fn test_wrapped_range() {
+        let mut map = VisibilityMap2d::default([10, 6]);
+        map[[3, 0]].opaque = true;
+        fov::compute_wrapped([0, 0], 100, [10, 6], &mut map);
+
+        let mut expected = VisibilityMap2d::default([10, 6]);
+        expected[[3, 0]].opaque = true;
+        fov::compute_wrapped([0, 0], 2, [10, 6], &mut expected);
+
+        assert!(map
+            .iter()
+            .map(|p| p.visible)
+            .eq(expected.iter().map(|p| p.visible)));
+        // Only seen from the left, never from around the seam
+        assert!(!map[[4, 0]].visible);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_wrapped_zero_size() {
+        let mut map = VisibilityMap2d::default([10, 10]);
+        fov::compute_wrapped([0, 0], 5, [0, 10], &mut map);
+    }
+
+    #[test]
+    fn test_unbounded() {
+        /// Records visible tiles without any bounds.
+        struct Infinite(Vec<IVec2>);
+
+        impl VisibilityMap for Infinite {
+            fn is_opaque(&self, p: impl GridPoint) -> bool {
+                p.x() == 3
+            }
+
+            fn is_in_bounds(&self, p: impl GridPoint) -> bool {
+                p.x() >= 0 && p.y() >= 0
+            }
+
+            fn set_visible(&mut self, p: impl GridPoint) {
+                self.0.push(p.as_ivec2());
+            }
+
+            fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+                RangeMetric::Euclidean.dist(a, b)
+            }
+        }
+
+        let mut map = Infinite(Vec::new());
+        fov::compute_unbounded([0, 0], 5, &mut map);
+
+        assert!(map.0.contains(&IVec2::new(-5, 0)));
+        assert!(map.0.contains(&IVec2::new(0, -5)));
+        assert!(map.0.contains(&IVec2::new(3, 0)));
+        assert!(!map.0.contains(&IVec2::new(4, 0)));
+    }
+
+    #[test]
+    fn test_bounded_by() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+
+        // An L shaped region
+        let in_bounds = |p: IVec2| p.x < 10 || p.y < 10;
+        fov::compute_bounded_by([5, 5], 8, in_bounds, &mut map);
+        assert!(map[[5, 12]].visible);
+        assert!(map[[12, 5]].visible);
+        assert!(!map[[10, 10]].visible);
+        assert!(!map[[11, 11]].visible);
+    }
+
+    #[test]
+    fn test_only_in_bounds() {
+        /// Panics if the fov reads or writes outside of its bounds.
+        struct Strict(Vec<bool>);
+
+        impl VisibilityMap for Strict {
+            fn is_opaque(&self, p: impl GridPoint) -> bool {
+                let i = grid_index(p, 10, 10).expect("read out of bounds");
+                i % 7 == 3
+            }
+
+            fn is_in_bounds(&self, p: impl GridPoint) -> bool {
+                grid_index(p, 10, 10).is_some()
+            }
+
+            fn set_visible(&mut self, p: impl GridPoint) {
+                let i = grid_index(p, 10, 10).expect("write out of bounds");
+                self.0[i] = true;
+            }
+
+            fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+                RangeMetric::Euclidean.dist(a, b)
+            }
+        }
+
+        let mut map = Strict(vec![false; 100]);
+        for origin in [[0, 0], [1, 1], [-2, -2], [9, 9], [12, 4], [5, 5]] {
+            fov::compute(origin, 6, &mut map);
+            fov::compute_with_symmetry(origin, 6, Symmetry::Full, &mut map);
+            fov::compute_overscan(origin, 6, &mut map);
+        }
+        assert!(map.0[0]);
+    }
+
+    #[test]
+    fn test_out_of_bounds() {
+        /// A map with a notch cut out of its top edge.
+        struct Notched(VisibilityMap2d);
+
+        impl VisibilityMap for Notched {
+            fn is_opaque(&self, p: impl GridPoint) -> bool {
+                self.0[p].opaque
+            }
+
+            fn is_in_bounds(&self, p: impl GridPoint) -> bool {
+                self.0.in_bounds(p) && !(p.x() == 4 && p.y() >= 4)
+            }
+
+            fn set_visible(&mut self, p: impl GridPoint) {
+                self.0[p].visible = true;
+            }
+
+            fn dist(&self, a: impl GridPoint, b: impl GridPoint) -> f32 {
+                RangeMetric::Euclidean.dist(a, b)
+            }
+        }
+
+        let mut map = Notched(VisibilityMap2d::default([10, 10]));
+        fov::compute_with_out_of_bounds([2, 8], 8, OutOfBounds::Opaque, &mut map);
+        assert!(!map.0[[6, 8]].visible);
+
+        // Vision passes over the notch
+        map.0.clear_visible();
+        fov::compute_with_out_of_bounds([2, 8], 8, OutOfBounds::Transparent, &mut map);
+        assert!(map.0[[6, 8]].visible);
+        assert!(!map.0[[4, 8]].visible);
+    }
+
+    #[test]
+    fn test_multi() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[15, 10]].opaque = true;
+        let sources = [(IVec2::new(5, 10), 3), (IVec2::new(15, 12), 4)];
+
+        let mut tiles = Vec::new();
+        fov::compute_multi(&sources, &map, |p| tiles.push(p));
+        let mut expected = fov::compute_tiles([5, 10], 3, &map);
+        expected.extend(fov::compute_tiles([15, 12], 4, &map));
+        expected.sort_unstable_by_key(|p| (p.y, p.x));
+        expected.dedup();
+        assert_eq!(tiles.len(), expected.len());
+        tiles.sort_unstable_by_key(|p| (p.y, p.x));
+        assert_eq!(tiles, expected);
+
+        // The tile behind the wall is not visible from either source
+        assert!(!tiles.contains(&IVec2::new(15, 9)));
+
+        let mut lit = Vec::new();
+        fov::compute_multi_lit(
+            &[(IVec2::new(5, 5), 4), (IVec2::new(7, 5), 4)],
+            Falloff::Linear,
+            &map,
+            |p, b| lit.push((p, b)),
+        );
+        let brightness = |p: IVec2| lit.iter().find(|(t, _)| *t == p).unwrap().1;
+        assert_eq!(
+            lit.iter().filter(|(p, _)| *p == IVec2::new(6, 5)).count(),
+            1
+        );
+        assert_eq!(brightness(IVec2::new(7, 5)), 1.0);
+        assert_eq!(brightness(IVec2::new(5, 5)), 1.0);
+        assert!(brightness(IVec2::new(6, 5)) < 1.0);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_multi_par() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[15, 10]].opaque = true;
+        let sources: Vec<_> = (0..20).map(|i| (IVec2::new(i, 29 - i), 4)).collect();
+
+        let mut visible = Grid::default([30, 30]);
+        fov::compute_multi_par(&sources, &map, &mut visible);
+
+        let mut expected = Grid::default([30, 30]);
+        fov::compute_multi(&sources, &map, |p| expected[p] = true);
+        assert!(visible.iter().eq(expected.iter()));
+    }
+
+    #[test]
+    fn test_multi_blended() {
+        let map = VisibilityMap2d::default([30, 30]);
+        let sources = [(IVec2::new(10, 15), 6), (IVec2::new(14, 15), 6)];
+        let lit = |blend| {
+            let mut lit = std::collections::HashMap::new();
+            fov::compute_multi_blended(&sources, Falloff::Linear, blend, &map, |p, b| {
+                assert!(lit.insert(p, b).is_none());
+            });
+            lit
+        };
+        let (max, add, screen) = (lit(Blend::Max), lit(Blend::Additive), lit(Blend::Screen));
+
+        let p = IVec2::new(12, 15);
+        let b = Falloff::Linear.brightness(2.0, 6.0);
+        assert_eq!(max[&p], b);
+        assert_eq!(add[&p], b + b);
+        assert_eq!(screen[&p], 1.0 - (1.0 - b) * (1.0 - b));
+
+        // Tiles lit by a single source are unaffected
+        let p = IVec2::new(5, 15);
+        assert_eq!(max[&p], add[&p]);
+        assert_eq!(max[&p], screen[&p]);
+        assert_eq!(max.len(), add.len());
+    }
+
+    #[test]
+    fn test_delta() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[12, 10]].opaque = true;
+
+        let delta = fov::compute_delta([10, 10], [10, 11], 3, &map);
+        assert!(delta.added.contains(&IVec2::new(10, 14)));
+        assert!(delta.removed.contains(&IVec2::new(10, 7)));
+        assert!(!delta.added.contains(&IVec2::new(14, 10)));
+        assert!(!delta.removed.contains(&IVec2::new(13, 10)));
+
+        let before = fov::compute_tiles([10, 10], 3, &map);
+        let after = fov::compute_tiles([10, 11], 3, &map);
+        assert!(delta
+            .added
+            .iter()
+            .all(|p| after.contains(p) && !before.contains(p)));
+        assert!(delta
+            .removed
+            .iter()
+            .all(|p| before.contains(p) && !after.contains(p)));
+        assert_eq!(
+            after.len(),
+            before.len() + delta.added.len() - delta.removed.len()
+        );
+
+        assert!(fov::compute_delta([10, 10], [10, 10], 3, &map).is_empty());
+    }
+
+    #[test]
+    fn test_fov_memory() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        let mut memory = FovMemory::new([30, 30]);
+
+        memory.update([5, 5], 3, &map);
+        assert!(memory.is_visible([5, 7]));
+        assert!(memory.is_explored([5, 7]));
+        assert!(!map[[5, 7]].visible);
+
+        map[[20, 5]].opaque = true;
+        memory.update([20, 7], 3, &map);
+        assert!(!memory.is_visible([5, 7]));
+        assert!(memory.is_explored([5, 7]));
+        assert!(memory.is_visible([20, 5]));
+        assert!(!memory.is_explored([20, 3]));
+        assert!(!memory.is_explored([-1, 5]));
+
+        memory.clear();
+        assert!(!memory.is_explored([5, 7]));
+    }
+
+    #[test]
+    fn test_reveal() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[17, 15]].opaque = true;
+        let mut explored = Grid::default([30, 30]);
+
+        let mut revealed = Vec::new();
+        fov::compute_reveal([15, 15], 4, &map, &mut explored, |p| revealed.push(p));
+        revealed.sort_unstable_by_key(|p| (p.y, p.x));
+        assert_eq!(revealed, fov::compute_tiles([15, 15], 4, &map));
+
+        // Only the tiles that weren't already explored are revealed
+        revealed.clear();
+        fov::compute_reveal([16, 15], 4, &map, &mut explored, |p| revealed.push(p));
+        assert!(revealed.contains(&IVec2::new(16, 19)));
+        assert!(!revealed.contains(&IVec2::new(15, 15)));
+        assert!(revealed.iter().all(|p| explored[*p]));
+        assert!(explored[[11, 15]]);
+    }
+
+    #[cfg(feature = "bevy")]
+    #[test]
+    fn test_plugin() {
+        use bevy::prelude::{App, Events};
+
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[17, 15]].opaque = true;
+        let mut app = App::new();
+        app.insert_resource(map)
+            .add_plugin(AdamFovPlugin::<VisibilityMap2d>::default());
+
+        let source = FovSource {
+            origin: IVec2::new(15, 15),
+            range: 5,
+        };
+        let entity = app.world.spawn().insert(source).id();
+        app.update();
+
+        let map = app.world.resource::<VisibilityMap2d>();
+        assert!(map[[15, 15]].visible);
+        assert!(map[[17, 15]].visible);
+        assert!(!map[[18, 15]].visible);
+        assert!(map[[15, 20]].visible);
+        assert!(!map[[15, 21]].visible);
+
+        let events = app.world.resource::<Events<FovComputed>>();
+        let sent: Vec<_> = events.get_reader().iter(events).copied().collect();
+        assert_eq!(sent, [FovComputed(entity)]);
+
+        app.world.despawn(entity);
+        app.update();
+        let map = app.world.resource::<VisibilityMap2d>();
+        assert!(map.iter().all(|p| !p.visible));
+    }
+
+    #[cfg(feature = "bevy")]
+    #[test]
+    fn test_fov_source_iter() {
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[17, 15]].opaque = true;
+        map[[14, 17]].opaque = true;
+        let source = FovSource {
+            origin: IVec2::new(15, 15),
+            range: 6,
+        };
+
+        let mut tiles: Vec<_> = source.iter(&map).map(|p| p.to_array()).collect();
+        tiles.sort_unstable();
+        tiles.dedup();
+
+        fov::compute(source.origin, source.range, &mut map);
+        let mut expected = Vec::new();
+        for y in 0..30 {
+            for x in 0..30 {
+                if map[[x, y]].visible {
+                    expected.push([x, y]);
+                }
+            }
+        }
+        expected.sort_unstable();
+        assert_eq!(tiles, expected);
+    }
+
+    #[cfg(feature = "bevy")]
+    #[test]
+    fn test_fov_source_collect() {
+        use bevy::prelude::{App, Component, Query, Res};
+
+        /// The tiles visible to a single source.
+        #[derive(Component, Default)]
+        struct VisibleTiles(Vec<IVec2>);
+
+        fn collect_tiles(
+            map: Res<VisibilityMap2d>,
+            mut q_sources: Query<(&FovSource, &mut VisibleTiles)>,
+        ) {
+            for (source, mut tiles) in q_sources.iter_mut() {
+                tiles.0 = source.iter(&*map).collect();
+            }
+        }
+
+        let mut map = VisibilityMap2d::default([30, 30]);
+        map[[17, 15]].opaque = true;
+        let mut app = App::new();
+        app.insert_resource(map.clone()).add_system(collect_tiles);
+
+        let sources = [
+            FovSource {
+                origin: IVec2::new(15, 15),
+                range: 5,
+            },
+            FovSource {
+                origin: IVec2::new(5, 8),
+                range: 3,
+            },
+        ];
+        let entities: Vec<_> = sources
+            .iter()
+            .map(|s| {
+                app.world
+                    .spawn()
+                    .insert(*s)
+                    .insert(VisibleTiles::default())
+                    .id()
+            })
+            .collect();
+        app.update();
+
+        for (entity, source) in entities.into_iter().zip(sources) {
+            let tiles = &app.world.get::<VisibleTiles>(entity).unwrap().0;
+            let expected: Vec<_> = fov::iter(source.origin, source.range, &map).collect();
+            assert!(!tiles.is_empty());
+            assert_eq!(*tiles, expected);
+        }
+        // Collecting the tiles doesn't touch the map itself
+        let map = app.world.resource::<VisibilityMap2d>();
+        assert!(map.iter().all(|p| !p.visible));
     }
 }
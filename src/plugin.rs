@@ -0,0 +1,75 @@
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+
+use crate::{fov, VisibilityMap, VisibilityMapUtility};
+
+/// Recomputes the fov of every [FovSource] entity on the map resource `M`.
+///
+/// The map's visible tiles are cleared and recomputed whenever the map or any
+/// source changes, sending a [FovComputed] event for each source.
+///
+/// `M` can be any resource that implements [VisibilityMap] and
+/// [VisibilityMapUtility], such as [VisibilityMap2d](crate::VisibilityMap2d).
+/// There's no separate trait for plugin maps: [VisibilityMapUtility] is what
+/// lets the plugin clear the previous fov before recomputing it.
+pub struct AdamFovPlugin<M>(PhantomData<M>);
+
+impl<M> Default for AdamFovPlugin<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M> Plugin for AdamFovPlugin<M>
+where
+    M: VisibilityMap + VisibilityMapUtility + Send + Sync + 'static,
+{
+    fn build(&self, app: &mut App) {
+        app.add_event::<FovComputed>().add_system(update_fov::<M>);
+    }
+}
+
+/// An entity that can see, from `origin` out to `range` tiles.
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FovSource {
+    pub origin: IVec2,
+    pub range: i32,
+}
+
+impl FovSource {
+    /// Returns an iterator over the tiles visible from this source, without
+    /// modifying the map. See [fov::iter].
+    ///
+    /// Useful for collecting the fov into a component, where a callback's
+    /// borrows would get in the way.
+    pub fn iter<'a, T: VisibilityMap>(&self, map: &'a T) -> fov::FovIter<'a, T> {
+        fov::iter(self.origin, self.range, map)
+    }
+}
+
+/// Sent when the fov for a [FovSource] entity has been recomputed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FovComputed(pub Entity);
+
+fn update_fov<M: VisibilityMap + VisibilityMapUtility + Send + Sync + 'static>(
+    map: Option<ResMut<M>>,
+    q_sources: Query<(Entity, &FovSource)>,
+    q_changed: Query<(), Changed<FovSource>>,
+    removed: RemovedComponents<FovSource>,
+    mut events: EventWriter<FovComputed>,
+) {
+    let mut map = match map {
+        Some(map) => map,
+        None => return,
+    };
+    if !map.is_changed() && q_changed.is_empty() && removed.iter().next().is_none() {
+        return;
+    }
+
+    map.clear_visible();
+    for (entity, source) in q_sources.iter() {
+        fov::compute(source.origin, source.range, &mut *map);
+        events.send(FovComputed(entity));
+    }
+}
@@ -0,0 +1,75 @@
+use sark_grids::Size2d;
+
+use crate::{fov, GridPoint, VisibilityMap};
+
+/// A precomputed table of which tiles are visible from every tile in a
+/// rectangular region starting at `[0,0]`.
+///
+/// Each tile stores one bit for every other tile in the region, so memory use
+/// grows with the square of the region's area - a 50x50 region takes about
+/// 780KB, a 100x100 region about 12.5MB.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VisibilityTable {
+    width: usize,
+    height: usize,
+    /// The number of words used for each source tile.
+    stride: usize,
+    bits: Vec<u64>,
+}
+
+impl VisibilityTable {
+    /// Compute the fov from every tile in the region of the given size.
+    ///
+    /// The map itself is not modified.
+    pub fn new<T: VisibilityMap>(size: impl Size2d, range: i32, map: &T) -> Self {
+        let (width, height) = (size.width(), size.height());
+        let len = width * height;
+        let stride = len.div_ceil(64);
+        let mut table = Self {
+            width,
+            height,
+            stride,
+            bits: vec![0; stride * len],
+        };
+
+        for a in 0..len {
+            let origin = table.pos(a);
+            let row = &mut table.bits[a * stride..(a + 1) * stride];
+            fov::compute_unique(origin, range, map, |p| {
+                if p.x >= 0 && p.y >= 0 && (p.x as usize) < width && (p.y as usize) < height {
+                    let b = p.y as usize * width + p.x as usize;
+                    row[b / 64] |= 1 << (b % 64);
+                }
+            });
+        }
+        table
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Whether `b` was visible from `a`. Tiles outside the region are never
+    /// visible.
+    pub fn is_visible(&self, a: impl GridPoint, b: impl GridPoint) -> bool {
+        match (self.index(a), self.index(b)) {
+            (Some(a), Some(b)) => self.bits[a * self.stride + b / 64] & (1 << (b % 64)) != 0,
+            _ => false,
+        }
+    }
+
+    fn pos(&self, i: usize) -> [i32; 2] {
+        [(i % self.width) as i32, (i / self.width) as i32]
+    }
+
+    fn index(&self, p: impl GridPoint) -> Option<usize> {
+        let p = p.as_ivec2();
+        (p.x >= 0 && p.y >= 0 && (p.x as usize) < self.width && (p.y as usize) < self.height)
+            .then(|| p.y as usize * self.width + p.x as usize)
+    }
+}
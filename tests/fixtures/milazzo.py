@@ -0,0 +1,209 @@
+"""Generate the expected tiles in tests/fixtures.rs.
+
+This is a line by line transcription of the `MyVisibility` class from Adam
+Milazzo's article, http://www.adammil.net/blog/v125_Roguelike_Vision_Algorithms.html#mine,
+kept as close to the C# as Python allows so it can be checked against the
+article by eye. It shares no code with the crate.
+
+Two choices are made where the article leaves them to the caller:
+
+* `is_visible` uses the "mostly symmetrical" line from the article's NOTE,
+  which is the variant this crate implements.
+* Tiles are in range if `x*x + y*y <= range*range`, and tiles outside the map
+  block light and are never set visible.
+
+Run it with `python3 tests/fixtures/milazzo.py`. It reads the wall layouts
+back out of tests/fixtures.rs and prints the expected tiles for each fixture.
+"""
+
+import os
+import re
+
+
+class Slope:
+    # represents the slope Y/X as a rational number
+    def __init__(self, y, x):
+        self.Y = y
+        self.X = x
+
+    def greater(self, y, x):
+        return self.Y * x > self.X * y
+
+    def greater_or_equal(self, y, x):
+        return self.Y * x >= self.X * y
+
+    def less(self, y, x):
+        return self.Y * x < self.X * y
+
+    def less_or_equal(self, y, x):
+        return self.Y * x <= self.X * y
+
+
+class MyVisibility:
+    def __init__(self, blocks_light, set_visible, in_range):
+        self._blocks_light = blocks_light
+        self._set_visible = set_visible
+        self._in_range = in_range
+
+    def compute(self, origin, range_limit):
+        self._set_visible(*origin)
+        for octant in range(8):
+            self._compute(octant, origin, range_limit, 1, Slope(1, 1), Slope(0, 1))
+
+    def _compute(self, octant, origin, range_limit, x, top, bottom):
+        while x <= range_limit:
+            if top.X == 1:
+                top_y = x
+            else:
+                top_y = ((x * 2 - 1) * top.Y + top.X) // (top.X * 2)
+                if self.blocks_light(x, top_y, octant, origin):
+                    if top.greater_or_equal(top_y * 2 + 1, x * 2) and not self.blocks_light(
+                        x, top_y + 1, octant, origin
+                    ):
+                        top_y += 1
+                else:
+                    ax = x * 2
+                    if self.blocks_light(x + 1, top_y + 1, octant, origin):
+                        ax += 1
+                    if top.greater(top_y * 2 + 1, ax):
+                        top_y += 1
+
+            if bottom.Y == 0:
+                bottom_y = 0
+            else:
+                bottom_y = ((x * 2 - 1) * bottom.Y + bottom.X) // (bottom.X * 2)
+                if (
+                    bottom.greater_or_equal(bottom_y * 2 + 1, x * 2)
+                    and self.blocks_light(x, bottom_y, octant, origin)
+                    and not self.blocks_light(x, bottom_y + 1, octant, origin)
+                ):
+                    bottom_y += 1
+
+            was_opaque = -1
+            y = top_y
+            while y >= bottom_y:
+                if self._in_range(x, y, range_limit):
+                    is_opaque = self.blocks_light(x, y, octant, origin)
+                    is_visible = is_opaque or (
+                        (y != top_y or top.greater_or_equal(y, x))
+                        and (y != bottom_y or bottom.less_or_equal(y, x))
+                    )
+                    if is_visible:
+                        self.set_visible(x, y, octant, origin)
+
+                    if x != range_limit:
+                        if is_opaque:
+                            if was_opaque == 0:
+                                nx, ny = x * 2, y * 2 + 1
+                                if self.blocks_light(x, y + 1, octant, origin):
+                                    nx -= 1
+                                if top.greater(ny, nx):
+                                    if y == bottom_y:
+                                        bottom = Slope(ny, nx)
+                                        break
+                                    else:
+                                        self._compute(octant, origin, range_limit, x + 1, top, Slope(ny, nx))
+                                else:
+                                    if y == bottom_y:
+                                        return
+                            was_opaque = 1
+                        else:
+                            if was_opaque > 0:
+                                nx, ny = x * 2, y * 2 + 1
+                                if self.blocks_light(x + 1, y + 1, octant, origin):
+                                    nx += 1
+                                if bottom.greater_or_equal(ny, nx):
+                                    return
+                                top = Slope(ny, nx)
+                            was_opaque = 0
+                y -= 1
+
+            if was_opaque != 0:
+                break
+            x += 1
+
+    def blocks_light(self, x, y, octant, origin):
+        return self._blocks_light(*transform(x, y, octant, origin))
+
+    def set_visible(self, x, y, octant, origin):
+        self._set_visible(*transform(x, y, octant, origin))
+
+
+def transform(x, y, octant, origin):
+    nx, ny = origin
+    if octant == 0:
+        nx += x; ny -= y
+    elif octant == 1:
+        nx += y; ny -= x
+    elif octant == 2:
+        nx -= y; ny -= x
+    elif octant == 3:
+        nx -= x; ny -= y
+    elif octant == 4:
+        nx -= x; ny += y
+    elif octant == 5:
+        nx -= y; ny += x
+    elif octant == 6:
+        nx += y; ny += x
+    elif octant == 7:
+        nx += x; ny += y
+    return nx, ny
+
+
+FIXTURE = re.compile(r'fn (\w+)\(\) \{.*?check\(\s*(\d+),\s*"(.*?)"', re.S)
+
+
+def layouts(source):
+    """Yield the name, range and rows of each fixture in `source`."""
+    for name, range_limit, layout in FIXTURE.findall(source):
+        yield name, int(range_limit), [r.strip() for r in layout.strip().splitlines()]
+
+
+def render(range_limit, rows):
+    width, height = len(rows[0]), len(rows)
+    walls, origin = set(), None
+    for i, row in enumerate(rows):
+        y = height - 1 - i
+        for x, c in enumerate(row):
+            if c in "#X":
+                walls.add((x, y))
+            elif c == "@":
+                origin = (x, y)
+
+    in_bounds = lambda x, y: 0 <= x < width and 0 <= y < height
+    visible = set()
+
+    def set_visible(x, y):
+        if in_bounds(x, y):
+            visible.add((x, y))
+
+    MyVisibility(
+        lambda x, y: not in_bounds(x, y) or (x, y) in walls,
+        set_visible,
+        lambda x, y, r: x * x + y * y <= r * r,
+    ).compute(origin, range_limit)
+
+    lines = []
+    for y in reversed(range(height)):
+        line = ""
+        for x in range(width):
+            p = (x, y)
+            if p == origin:
+                line += "@"
+            elif p in walls:
+                line += "#" if p in visible else "X"
+            else:
+                line += "." if p in visible else "-"
+        lines.append(line)
+    return lines
+
+
+if __name__ == "__main__":
+    path = os.path.join(os.path.dirname(__file__), "..", "fixtures.rs")
+    with open(path) as f:
+        source = f.read()
+    for name, range_limit, rows in layouts(source):
+        print(name, range_limit)
+        for line in render(range_limit, rows):
+            print("        " + line)
+        print()